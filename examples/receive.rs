@@ -1,8 +1,15 @@
 #![cfg(feature = "linux")]
 
-use bluer::{DeviceEvent, DeviceProperty, DiscoveryFilter};
+use std::time::Duration;
+
+use bluer::{Address, DeviceEvent, DeviceProperty, DiscoveryFilter};
+use chrono::{DateTime, Utc};
 use futures::stream::StreamExt;
 use remote_id::codec::decode;
+use remote_id::codec::ServiceDataFrame;
+use remote_id::stats::ReceptionStats;
+use remote_id::tracker::UasTracker;
+use tokio::sync::mpsc;
 use uuid::Uuid;
 
 const REMOTE_ID_SERVICE_UUID: Uuid = Uuid::from_u128(remote_id::REMOTE_ID_SERVICE_UUID);
@@ -10,7 +17,7 @@ const REMOTE_ID_SERVICE_UUID: Uuid = Uuid::from_u128(remote_id::REMOTE_ID_SERVIC
 /// Receive Example Using bluer
 ///
 /// Will scan for bluetooth devices and listen to their broadcast messages,
-/// printing parsable remote id messages
+/// printing the merged state of every aircraft on each parsable remote id message
 #[tokio::main]
 async fn main() {
     let session = bluer::Session::new()
@@ -50,24 +57,54 @@ async fn main() {
         .expect("failed to start device discovery session");
     println!("Scanning for BLE advertisements...\n");
 
-    while let Some(event_result) = discovery.next().await {
-        if let bluer::AdapterEvent::DeviceAdded(address) = event_result {
-            if let Ok(device) = adapter.device(address) {
-                println!("Device Discovered: {} {:?}", address, device.alias().await);
-                tokio::spawn(handle_device(device));
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let mut tracker = UasTracker::new(Duration::from_secs(30));
+    let mut stats = ReceptionStats::new();
+    let mut expire_interval = tokio::time::interval(Duration::from_secs(1));
+
+    loop {
+        tokio::select! {
+            Some(event_result) = discovery.next() => {
+                if let bluer::AdapterEvent::DeviceAdded(address) = event_result {
+                    if let Ok(device) = adapter.device(address) {
+                        println!("Device Discovered: {} {:?}", address, device.alias().await);
+                        tokio::spawn(handle_device(device, sender.clone()));
+                    }
+                }
+            }
+            Some((mac_addr, frame, received)) = receiver.recv() => {
+                stats.record(mac_addr, &frame);
+                let ingested = tracker.ingest(mac_addr, frame.message, received);
+                for merge in &ingested.merges {
+                    println!("{:?} is {:?}", merge.merged, merge.track_id);
+                }
+                println!("{mac_addr}: {:?}", ingested.state);
+            }
+            _ = expire_interval.tick() => {
+                for state in tracker.expire(Utc::now()) {
+                    for address in &state.addresses {
+                        println!("{address}: lost, {:?}", stats.total(address));
+                        stats.remove(address);
+                    }
+                }
             }
         }
     }
 }
 
-async fn handle_device(device: bluer::Device) {
+async fn handle_device(
+    device: bluer::Device,
+    sender: mpsc::UnboundedSender<(Address, ServiceDataFrame, DateTime<Utc>)>,
+) {
     let mut events = device.events().await.expect("failed to get events");
     let mac_addr = device.address();
     while let Some(ev) = events.next().await {
         if let DeviceEvent::PropertyChanged(DeviceProperty::ServiceData(service_data)) = ev {
             if let Some(service_data) = service_data.get(&REMOTE_ID_SERVICE_UUID) {
-                if let Some(rid_msg) = decode::from_service_data(&service_data) {
-                    println!("{mac_addr}: {rid_msg:?}");
+                if let Ok(frame) = decode::from_service_data_frame(service_data) {
+                    if sender.send((mac_addr, frame, Utc::now())).is_err() {
+                        return;
+                    }
                 }
             }
         }
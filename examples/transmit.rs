@@ -3,13 +3,11 @@
 use bluer::adv::Advertisement;
 use chrono::DateTime;
 use remote_id::{
-    codec::{copy_to_id, encode},
+    codec::encode,
     data::{
         basic_id::BasicId,
-        location::Location,
-        system::{
-            ClassificationType, OperatorLocationType, System, UaCategory, UaClass, UaClassification,
-        },
+        location::{HeightType, HorizontalAccuracy, Location, OperationalStatus},
+        system::{System, UaCategory, UaClass},
         RemoteIDMessage,
     },
 };
@@ -30,54 +28,51 @@ async fn main() -> bluer::Result<()> {
     println!("ADAPTER ADDRESS: {}", adapter.address().await?);
 
     let messages = [
-        RemoteIDMessage::BasicID(BasicId {
-            id_type: remote_id::data::basic_id::IdType::SerialNumber,
-            ua_type: remote_id::data::basic_id::UAType::None,
-            uas_id: copy_to_id("1234567890123456789\0".as_bytes()),
-        }),
-        RemoteIDMessage::Location(Location {
-            operational_status: remote_id::data::location::OperationalStatus::Ground,
-            height_type: remote_id::data::location::HeightType::AboveGroundLevel,
-            speed: 10.0,
-            vertical_speed: 10.0,
-            pressure_altitude: 0.0,
-            geodetic_altitude: 0.0,
-            track_direction: 0,
-            horizontal_accuracy: remote_id::data::location::HorizontalAccuracy::LessThan_10_NM,
-            vertical_accuracy: remote_id::data::location::VerticalAccuracy::Unknown,
-            latidute: 50.0828829,
-            longitude: 8.6959298,
-            height: 0.0,
-            baro_altitude_accuracy: remote_id::data::location::VerticalAccuracy::Unknown,
-            speed_accuracy: remote_id::data::location::SpeedAccuracy::Unknown,
-            timestamp: 0.0,
-            timestamp_accuracy: None,
-        }),
-        RemoteIDMessage::System(System {
-            classification_type: ClassificationType::EuropeanUnion,
-            operator_location_type: OperatorLocationType::TakeOff,
-            operator_latidute: 50.084147,
-            operator_longitude: 8.694112,
-            operator_altitude: 212.,
-            area_ceiling: -999.,
-            area_count: 1,
-            area_floor: -100.,
-            area_radius: 249.,
-            ua_classification: UaClassification {
-                category: UaCategory::Open,
-                class: UaClass::Undefined,
-            },
-            timestamp: DateTime::parse_from_rfc3339(&"2024-07-04T14:05:54Z")
-                .unwrap()
-                .to_utc(),
-        }),
+        RemoteIDMessage::BasicID(
+            BasicId::builder()
+                .serial_number("1234E56789012345678")
+                .build()
+                .unwrap(),
+        ),
+        RemoteIDMessage::Location(
+            Location::builder()
+                .operational_status(OperationalStatus::Ground)
+                .position(50.0828829, 8.6959298)
+                .speed(10.0)
+                .vertical_speed(10.0)
+                .pressure_altitude(0.0)
+                .geodetic_altitude(0.0)
+                .track_direction(0)
+                .height(0.0, HeightType::AboveGroundLevel)
+                .horizontal_accuracy(HorizontalAccuracy::LessThan_10_NM)
+                .timestamp(0.0)
+                .build()
+                .unwrap(),
+        ),
+        RemoteIDMessage::System(
+            System::builder()
+                .operator_position(50.084147, 8.694112)
+                .operator_altitude(212.)
+                .area(1, 249.)
+                .area_ceiling(-999.)
+                .area_floor(-100.)
+                .eu_classification(UaCategory::Open, UaClass::Undefined)
+                .timestamp(
+                    DateTime::parse_from_rfc3339("2024-07-04T14:05:54Z")
+                        .unwrap()
+                        .to_utc(),
+                )
+                .build()
+                .unwrap(),
+        ),
     ]
     .into_iter()
     .cycle()
     .take(100);
 
-    for (i, m) in messages.enumerate() {
-        let message_bytes = encode::to_service_data(&m, (i % 255) as u8);
+    let mut encoder = encode::ServiceDataEncoder::new();
+    for m in messages {
+        let message_bytes = encoder.encode(&m).expect("failed to encode message");
 
         let le_advertisement = Advertisement {
             advertisement_type: bluer::adv::Type::Peripheral,
@@ -0,0 +1,507 @@
+//! Ed25519 signatures carried in authentication messages
+//!
+//! The signature covers the page 0 header as encoded (auth type, last page index, length
+//! and the timestamp in seconds since 2019-01-01, little endian), followed by the encoded
+//! 25 byte messages it authenticates. For the specific authentication method the data starts
+//! with the method type, which is signed between header and messages. Received signatures
+//! are best verified against the message buffers as received, see [`SignedContent::Raw`].
+//!
+//! | Auth type                      | Signed messages | Authentication data   |
+//! |--------------------------------|-----------------|-----------------------|
+//! | UAS ID Signature               | Basic ID        | signature             |
+//! | Operator ID Signature          | Operator ID     | signature             |
+//! | Message Set Signature          | message set     | signature             |
+//! | Specific Authentication Method | message set     | method type, signature |
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey, SIGNATURE_LENGTH};
+
+use crate::codec::decode::message_type;
+use crate::codec::encode::{check_timestamp, try_to_message_buffer, EncodeError};
+use crate::codec::MessageType;
+use crate::data::authentication::{
+    AuthType, AuthenticationData, FIRST_PAGE_DATA_BYTE_SIZE, PAGE_DATA_BYTE_SIZE,
+};
+use crate::data::basic_id::BasicId;
+use crate::data::message_pack::MESSAGE_BYTE_SIZE;
+use crate::data::operator_id::OperatorId;
+use crate::data::RemoteIDMessage;
+use crate::REMOTE_ID_EPOCH;
+
+/// The messages authenticated by a signature
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SignedContent<'a> {
+    UasId(&'a BasicId),
+    OperatorId(&'a OperatorId),
+    MessageSet(&'a [RemoteIDMessage]),
+    SpecificMethod {
+        /// Specific authentication method type, e.g. one of the DRIP authentication types
+        method_type: u8,
+        messages: &'a [RemoteIDMessage],
+    },
+    /// Message buffers as received, of whichever auth type the authentication data has
+    ///
+    /// Decoded messages are encoded again for signing, which only reproduces the received
+    /// bytes for messages this crate can encode losslessly. Can only be verified, not signed.
+    Raw(&'a [[u8; MESSAGE_BYTE_SIZE]]),
+}
+
+impl SignedContent<'_> {
+    /// The auth type of the content, `None` for raw messages
+    pub fn auth_type(&self) -> Option<AuthType> {
+        match self {
+            SignedContent::UasId(_) => Some(AuthType::UasIdSignature),
+            SignedContent::OperatorId(_) => Some(AuthType::OperatorIdSignature),
+            SignedContent::MessageSet(_) => Some(AuthType::MessageSetSignature),
+            SignedContent::SpecificMethod { .. } => Some(AuthType::SpecificAuthenticationMethod),
+            SignedContent::Raw(_) => None,
+        }
+    }
+
+    /// Authentication data preceding the signature
+    fn header(&self) -> Option<u8> {
+        match self {
+            SignedContent::SpecificMethod { method_type, .. } => Some(*method_type),
+            _ => None,
+        }
+    }
+
+    /// Encode the messages with their protocol version
+    fn encode_messages(&self) -> Result<Vec<[u8; MESSAGE_BYTE_SIZE]>, AuthError> {
+        let messages = match self {
+            SignedContent::UasId(basic_id) => vec![RemoteIDMessage::BasicID((*basic_id).clone())],
+            SignedContent::OperatorId(operator_id) => {
+                vec![RemoteIDMessage::OperatorId(**operator_id)]
+            }
+            SignedContent::MessageSet(messages)
+            | SignedContent::SpecificMethod { messages, .. } => messages.to_vec(),
+            SignedContent::Raw(buffers) => return Ok(buffers.to_vec()),
+        };
+
+        let mut buffers = vec![[0; MESSAGE_BYTE_SIZE]; messages.len()];
+        for (message, buffer) in messages.iter().zip(&mut buffers) {
+            try_to_message_buffer(message, buffer)?;
+        }
+        Ok(buffers)
+    }
+
+    fn signed_bytes(
+        &self,
+        auth_type: AuthType,
+        header: &[u8],
+        timestamp: &DateTime<Utc>,
+    ) -> Result<Vec<u8>, AuthError> {
+        check_timestamp(timestamp, "timestamp")?;
+
+        let length = header.len() + SIGNATURE_LENGTH;
+        let last_page_index = length
+            .saturating_sub(FIRST_PAGE_DATA_BYTE_SIZE)
+            .div_ceil(PAGE_DATA_BYTE_SIZE);
+        let remote_id_timestamp = (timestamp.timestamp() - REMOTE_ID_EPOCH) as u32;
+
+        let buffers = self.encode_messages()?;
+        let mut bytes = vec![auth_type.into(), last_page_index as u8, length as u8];
+        bytes.extend_from_slice(&remote_id_timestamp.to_le_bytes());
+        bytes.extend_from_slice(header);
+        for buffer in &buffers {
+            match message_type(buffer) {
+                Ok(MessageType::Auth | MessageType::MessagePack) | Err(_) => {
+                    return Err(AuthError::UnsignableMessage)
+                }
+                Ok(_) => bytes.extend_from_slice(buffer),
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AuthError {
+    /// The authentication data is of a different type than the signed content, or no signature
+    AuthTypeMismatch {
+        expected: AuthType,
+        actual: AuthType,
+    },
+    /// The authentication data is too short or too long for a signature
+    InvalidLength(usize),
+    /// The specific authentication method type does not match
+    MethodTypeMismatch { expected: u8, actual: u8 },
+    /// Authentication, message packs and raw messages can not be signed
+    UnsignableMessage,
+    /// A signed message or the timestamp can not be encoded
+    Encode(EncodeError),
+    /// The signature does not match the public key and the signed content
+    InvalidSignature,
+}
+
+impl From<EncodeError> for AuthError {
+    fn from(value: EncodeError) -> Self {
+        AuthError::Encode(value)
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::AuthTypeMismatch { expected, actual } => {
+                write!(f, "expected auth type {expected:?}, got {actual:?}")
+            }
+            AuthError::InvalidLength(len) => write!(f, "invalid authentication data length: {len}"),
+            AuthError::MethodTypeMismatch { expected, actual } => {
+                write!(f, "expected method type {expected}, got {actual}")
+            }
+            AuthError::UnsignableMessage => write!(f, "message can not be signed"),
+            AuthError::Encode(e) => write!(f, "{e}"),
+            AuthError::InvalidSignature => write!(f, "invalid signature"),
+        }
+    }
+}
+
+impl core::error::Error for AuthError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            AuthError::Encode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Signs the messages of a transmitter
+#[derive(Debug, Clone)]
+pub struct AuthSigner {
+    key: SigningKey,
+}
+
+impl AuthSigner {
+    pub fn new(key: SigningKey) -> Self {
+        Self { key }
+    }
+
+    pub fn verifying_key(&self) -> VerifyingKey {
+        self.key.verifying_key()
+    }
+
+    /// Create the authentication data, to be split with
+    /// [`to_authentication_pages`](crate::codec::encode::to_authentication_pages)
+    pub fn sign(
+        &self,
+        content: SignedContent,
+        timestamp: DateTime<Utc>,
+    ) -> Result<AuthenticationData, AuthError> {
+        let auth_type = content.auth_type().ok_or(AuthError::UnsignableMessage)?;
+        let header = content.header();
+        let signature =
+            self.key
+                .sign(&content.signed_bytes(auth_type, header.as_slice(), &timestamp)?);
+
+        let mut data = header.as_slice().to_vec();
+        data.extend_from_slice(&signature.to_bytes());
+        Ok(AuthenticationData {
+            auth_type,
+            timestamp,
+            data,
+        })
+    }
+}
+
+/// Verify reassembled authentication data against the received messages
+pub fn verify(
+    public_key: &VerifyingKey,
+    auth: &AuthenticationData,
+    content: SignedContent,
+) -> Result<(), AuthError> {
+    // raw messages take the received auth type, as long as it is a signature
+    let expected = content.auth_type().unwrap_or(match auth.auth_type {
+        auth_type @ (AuthType::UasIdSignature
+        | AuthType::OperatorIdSignature
+        | AuthType::MessageSetSignature
+        | AuthType::SpecificAuthenticationMethod) => auth_type,
+        _ => AuthType::MessageSetSignature,
+    });
+    if auth.auth_type != expected {
+        return Err(AuthError::AuthTypeMismatch {
+            expected,
+            actual: auth.auth_type,
+        });
+    }
+
+    // the method type of raw messages is the received one, it is covered by the signature
+    let header_len = match content {
+        SignedContent::Raw(_) if auth.auth_type == AuthType::SpecificAuthenticationMethod => 1,
+        _ => content.header().as_slice().len(),
+    };
+    if auth.data.len() != header_len + SIGNATURE_LENGTH {
+        return Err(AuthError::InvalidLength(auth.data.len()));
+    }
+    let (header, signature) = auth.data.split_at(header_len);
+    if let Some(expected) = content.header() {
+        if header[0] != expected {
+            return Err(AuthError::MethodTypeMismatch {
+                expected,
+                actual: header[0],
+            });
+        }
+    }
+
+    let signature = Signature::from_slice(signature).map_err(|_| AuthError::InvalidSignature)?;
+    let signed_bytes = content.signed_bytes(auth.auth_type, header, &auth.timestamp)?;
+    public_key
+        .verify_strict(&signed_bytes, &signature)
+        .map_err(|_| AuthError::InvalidSignature)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codec::decode::from_message_buffer;
+    use crate::codec::encode::{to_authentication_pages, to_message_buffer};
+    use crate::data::authentication::AuthenticationReassembler;
+    use crate::data::location::Location;
+
+    fn signer() -> AuthSigner {
+        AuthSigner::new(SigningKey::from_bytes(&[7; 32]))
+    }
+
+    fn timestamp() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-07-04T14:05:54Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    fn basic_id() -> BasicId {
+        BasicId::builder()
+            .serial_number("1596F359746167260749")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn uas_id_signature_over_the_air() {
+        let signer = signer();
+        let basic_id = basic_id();
+        let auth = signer
+            .sign(SignedContent::UasId(&basic_id), timestamp())
+            .unwrap();
+        assert_eq!(AuthType::UasIdSignature, auth.auth_type);
+
+        // transmit the pages and reassemble them
+        let mut reassembler = AuthenticationReassembler::new();
+        let mut received = None;
+        for page in to_authentication_pages(&auth).unwrap() {
+            let mut buffer = [0; MESSAGE_BYTE_SIZE];
+            to_message_buffer(&RemoteIDMessage::Authentication(page), &mut buffer);
+            let RemoteIDMessage::Authentication(page) = from_message_buffer(&buffer).unwrap()
+            else {
+                panic!("expected authentication");
+            };
+            received = reassembler.push(page);
+        }
+        let received = received.unwrap();
+
+        let public_key = signer.verifying_key();
+        assert_eq!(
+            Ok(()),
+            verify(&public_key, &received, SignedContent::UasId(&basic_id))
+        );
+
+        let mut other = basic_id;
+        other.uas_id = BasicId::builder()
+            .serial_number("1596F359746167260748")
+            .build()
+            .unwrap()
+            .uas_id;
+        assert_eq!(
+            Err(AuthError::InvalidSignature),
+            verify(&public_key, &received, SignedContent::UasId(&other))
+        );
+    }
+
+    #[test]
+    fn operator_id_signature() {
+        let operator_id = OperatorId::builder()
+            .operator_id("FIN87astrdge12k8")
+            .build()
+            .unwrap();
+        let auth = signer()
+            .sign(SignedContent::OperatorId(&operator_id), timestamp())
+            .unwrap();
+
+        let content = SignedContent::OperatorId(&operator_id);
+        assert_eq!(Ok(()), verify(&signer().verifying_key(), &auth, content));
+
+        let other_key = SigningKey::from_bytes(&[8; 32]).verifying_key();
+        assert_eq!(
+            Err(AuthError::InvalidSignature),
+            verify(&other_key, &auth, content)
+        );
+        assert_eq!(
+            Err(AuthError::AuthTypeMismatch {
+                expected: AuthType::UasIdSignature,
+                actual: AuthType::OperatorIdSignature
+            }),
+            verify(
+                &signer().verifying_key(),
+                &auth,
+                SignedContent::UasId(&basic_id())
+            )
+        );
+    }
+
+    #[test]
+    fn message_set_signature() {
+        let messages = [
+            RemoteIDMessage::BasicID(basic_id()),
+            RemoteIDMessage::Location(
+                Location::builder()
+                    .position(49.8748544, 8.9121736)
+                    .build()
+                    .unwrap(),
+            ),
+        ];
+        let public_key = signer().verifying_key();
+
+        let auth = signer()
+            .sign(SignedContent::MessageSet(&messages), timestamp())
+            .unwrap();
+        assert_eq!(
+            Ok(()),
+            verify(&public_key, &auth, SignedContent::MessageSet(&messages))
+        );
+        assert_eq!(
+            Err(AuthError::InvalidSignature),
+            verify(
+                &public_key,
+                &auth,
+                SignedContent::MessageSet(&messages[..1])
+            )
+        );
+
+        let mut replayed = auth.clone();
+        replayed.timestamp += chrono::Duration::seconds(1);
+        assert_eq!(
+            Err(AuthError::InvalidSignature),
+            verify(&public_key, &replayed, SignedContent::MessageSet(&messages))
+        );
+    }
+
+    #[test]
+    fn raw_signature_of_older_versions() {
+        // F3411-19 Basic ID and F3411-20 Location as received from another transmitter
+        let mut basic_id = [0; MESSAGE_BYTE_SIZE];
+        basic_id[..22].copy_from_slice(b"\x00\x101596F359746167260749");
+        let location = [
+            17, 34, 157, 0, 0, 143, 76, 186, 29, 192, 227, 79, 5, 77, 9, 116, 9, 208, 7, 91, 4, 26,
+            14, 0, 0,
+        ];
+        let buffers = [basic_id, location];
+
+        // Message Set Signature, last page index 3, length 64, timestamp
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let mut signed = [3, 3, 64].to_vec();
+        signed
+            .extend_from_slice(&((timestamp().timestamp() - REMOTE_ID_EPOCH) as u32).to_le_bytes());
+        signed.extend_from_slice(&basic_id);
+        signed.extend_from_slice(&location);
+        let auth = AuthenticationData {
+            auth_type: AuthType::MessageSetSignature,
+            timestamp: timestamp(),
+            data: key.sign(&signed).to_bytes().to_vec(),
+        };
+
+        let public_key = key.verifying_key();
+        assert_eq!(
+            Ok(()),
+            verify(&public_key, &auth, SignedContent::Raw(&buffers))
+        );
+        assert_eq!(
+            Err(AuthError::InvalidSignature),
+            verify(&public_key, &auth, SignedContent::Raw(&buffers[..1]))
+        );
+
+        let messages = buffers.map(|buffer| from_message_buffer(&buffer).unwrap());
+        assert_eq!(
+            Ok(()),
+            verify(&public_key, &auth, SignedContent::MessageSet(&messages))
+        );
+        assert_eq!(
+            Err(AuthError::UnsignableMessage),
+            signer().sign(SignedContent::Raw(&buffers), timestamp())
+        );
+    }
+
+    #[test]
+    fn reject_signature_of_other_auth_type() {
+        let basic_id = basic_id();
+        let public_key = signer().verifying_key();
+        let mut auth = signer()
+            .sign(SignedContent::UasId(&basic_id), timestamp())
+            .unwrap();
+
+        let mut buffer = [0; MESSAGE_BYTE_SIZE];
+        to_message_buffer(&RemoteIDMessage::BasicID(basic_id.clone()), &mut buffer);
+        assert_eq!(
+            Ok(()),
+            verify(&public_key, &auth, SignedContent::Raw(&[buffer]))
+        );
+
+        auth.auth_type = AuthType::MessageSetSignature;
+        let messages = [RemoteIDMessage::BasicID(basic_id)];
+        assert_eq!(
+            Err(AuthError::InvalidSignature),
+            verify(&public_key, &auth, SignedContent::MessageSet(&messages))
+        );
+        assert_eq!(
+            Err(AuthError::InvalidSignature),
+            verify(&public_key, &auth, SignedContent::Raw(&[buffer]))
+        );
+
+        for auth_type in [
+            AuthType::None,
+            AuthType::NetworkRemoteId,
+            AuthType::Reserved(6),
+            AuthType::PrivateUse(10),
+        ] {
+            auth.auth_type = auth_type;
+            assert_eq!(
+                Err(AuthError::AuthTypeMismatch {
+                    expected: AuthType::MessageSetSignature,
+                    actual: auth_type
+                }),
+                verify(&public_key, &auth, SignedContent::Raw(&[buffer]))
+            );
+        }
+    }
+
+    #[test]
+    fn specific_method_signature() {
+        let messages = [RemoteIDMessage::BasicID(basic_id())];
+        let content = SignedContent::SpecificMethod {
+            method_type: 2,
+            messages: &messages,
+        };
+
+        let auth = signer().sign(content, timestamp()).unwrap();
+        assert_eq!(2, auth.data[0]);
+        assert_eq!(1 + SIGNATURE_LENGTH, auth.data.len());
+        assert_eq!(Ok(()), verify(&signer().verifying_key(), &auth, content));
+
+        assert_eq!(
+            Err(AuthError::MethodTypeMismatch {
+                expected: 3,
+                actual: 2
+            }),
+            verify(
+                &signer().verifying_key(),
+                &auth,
+                SignedContent::SpecificMethod {
+                    method_type: 3,
+                    messages: &messages
+                }
+            )
+        );
+    }
+}
@@ -0,0 +1,832 @@
+//! Remote ID from pcap and pcapng captures
+//!
+//! The capture is decoded from memory, e.g. a file read with `std::fs::read`. Remote ID is
+//! extracted from packets of these link types:
+//!
+//! | Link type | Name                        | Frames                                          |
+//! |-----------|-----------------------------|-------------------------------------------------|
+//! | 105       | IEEE802_11                  | Wi-Fi beacons and NAN service discovery frames  |
+//! | 127       | IEEE802_11_RADIOTAP         | Wi-Fi beacons and NAN service discovery frames  |
+//! | 251       | BLUETOOTH_LE_LL             | BLE legacy and extended advertising             |
+//! | 256       | BLUETOOTH_LE_LL_WITH_PHDR   | BLE legacy and extended advertising, coded PHY  |
+//!
+//! Packets of other link types, packets without Remote ID and malformed packets are skipped,
+//! as captures usually contain plenty of other traffic.
+
+use alloc::vec::Vec;
+
+use chrono::{DateTime, Utc};
+
+use crate::codec::decode::DecodeError;
+use crate::codec::ServiceDataFrame;
+use crate::transport::{ble, wifi_beacon, wifi_nan};
+
+pub const LINKTYPE_IEEE802_11: u32 = 105;
+pub const LINKTYPE_IEEE802_11_RADIOTAP: u32 = 127;
+pub const LINKTYPE_BLUETOOTH_LE_LL: u32 = 251;
+pub const LINKTYPE_BLUETOOTH_LE_LL_WITH_PHDR: u32 = 256;
+
+const PCAP_MAGIC_MICROS: u32 = 0xA1B2_C3D4;
+const PCAP_MAGIC_NANOS: u32 = 0xA1B2_3C4D;
+const PCAP_HEADER_BYTE_SIZE: usize = 24;
+const PCAP_RECORD_HEADER_BYTE_SIZE: usize = 16;
+
+/// The block type reads the same in both byte orders
+const PCAPNG_SECTION_HEADER: [u8; 4] = [0x0A, 0x0D, 0x0D, 0x0A];
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x0000_0006;
+/// Block type, length and trailing length
+const PCAPNG_BLOCK_OVERHEAD_BYTE_SIZE: usize = 12;
+const PCAPNG_ENHANCED_PACKET_HEADER_BYTE_SIZE: usize = 20;
+
+const OPTION_END_OF_OPT: u16 = 0;
+const OPTION_IF_TSRESOL: u16 = 9;
+const OPTION_IF_TSOFFSET: u16 = 14;
+
+/// Frame control, duration, three addresses and sequence control
+const MAC_HEADER_BYTE_SIZE: usize = 24;
+const MAC_SUBTYPE_BEACON: u8 = 0x8;
+const MAC_SUBTYPE_ACTION: u8 = 0xD;
+const MAC_SUBTYPE_ACTION_NO_ACK: u8 = 0xE;
+
+const RADIOTAP_PRESENT_TSFT: u32 = 1 << 0;
+const RADIOTAP_PRESENT_FLAGS: u32 = 1 << 1;
+const RADIOTAP_PRESENT_EXT: u32 = 1 << 31;
+const RADIOTAP_FLAG_FCS: u8 = 0x10;
+const FCS_BYTE_SIZE: usize = 4;
+
+/// Access address of all advertising channel packets
+const ADVERTISING_ACCESS_ADDRESS: u32 = 0x8E89_BED6;
+const PDU_ADV_IND: u8 = 0x0;
+const PDU_ADV_NONCONN_IND: u8 = 0x2;
+const PDU_SCAN_RSP: u8 = 0x4;
+const PDU_ADV_SCAN_IND: u8 = 0x6;
+/// Also AUX_ADV_IND and AUX_CHAIN_IND on the secondary channels
+const PDU_ADV_EXT_IND: u8 = 0x7;
+const EXTENDED_HEADER_ADV_A: u8 = 0x01;
+const ADDRESS_BYTE_SIZE: usize = 6;
+
+/// RF channel, signal and noise power, access address offenses, reference access address and flags
+const PHDR_BYTE_SIZE: usize = 10;
+const PHDR_CRC_CHECKED: u16 = 0x0400;
+const PHDR_CRC_VALID: u16 = 0x0800;
+const PHDR_PHY_MASK: u16 = 0xC000;
+const PHDR_PHY_CODED: u16 = 0x8000;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CaptureError {
+    /// The capture starts with neither a pcap nor a pcapng header
+    UnknownFormat,
+    /// A record or block extends beyond the end of the capture
+    Truncated,
+    /// A pcapng block is malformed
+    InvalidBlock,
+    /// A packet refers to an interface not described before
+    UnknownInterface(u32),
+    /// The timestamp resolution or a timestamp is out of range
+    InvalidTimestamp,
+}
+
+impl core::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CaptureError::UnknownFormat => write!(f, "neither pcap nor pcapng"),
+            CaptureError::Truncated => write!(f, "capture truncated"),
+            CaptureError::InvalidBlock => write!(f, "invalid pcapng block"),
+            CaptureError::UnknownInterface(id) => write!(f, "unknown interface {id}"),
+            CaptureError::InvalidTimestamp => write!(f, "invalid timestamp"),
+        }
+    }
+}
+
+impl core::error::Error for CaptureError {}
+
+/// A Remote ID frame read from a capture
+#[derive(Debug, Clone, PartialEq)]
+pub struct Captured {
+    pub timestamp: DateTime<Utc>,
+    /// BLE advertiser address or Wi-Fi transmitter address, most significant byte first
+    pub address: [u8; ADDRESS_BYTE_SIZE],
+    pub frame: ServiceDataFrame,
+}
+
+/// Iterates over the Remote ID frames of a pcap or pcapng capture
+///
+/// Stops after the first error of the capture format.
+#[derive(Debug, Clone)]
+pub struct CaptureReader<'a> {
+    packets: Packets<'a>,
+}
+
+impl<'a> CaptureReader<'a> {
+    pub fn new(capture: &'a [u8]) -> Result<Self, CaptureError> {
+        let packets = if capture.starts_with(&PCAPNG_SECTION_HEADER) {
+            Packets::Pcapng(PcapngPackets::new(capture))
+        } else {
+            Packets::Pcap(PcapPackets::new(capture)?)
+        };
+        Ok(CaptureReader { packets })
+    }
+}
+
+impl Iterator for CaptureReader<'_> {
+    type Item = Result<Captured, CaptureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let packet = match self.packets.next()? {
+                Ok(packet) => packet,
+                Err(e) => return Some(Err(e)),
+            };
+            if let Ok((address, frame)) = from_packet(packet.link_type, packet.data) {
+                return Some(Ok(Captured {
+                    timestamp: packet.timestamp,
+                    address,
+                    frame,
+                }));
+            }
+        }
+    }
+}
+
+/// Decode a single captured packet of one of the supported link types
+///
+/// Returns the BLE advertiser address or Wi-Fi transmitter address, most significant byte
+/// first, together with the frame.
+pub fn from_packet(
+    link_type: u32,
+    packet: &[u8],
+) -> Result<([u8; ADDRESS_BYTE_SIZE], ServiceDataFrame), DecodeError> {
+    match link_type {
+        LINKTYPE_IEEE802_11 => from_ieee802_11(packet),
+        LINKTYPE_IEEE802_11_RADIOTAP => from_ieee802_11(strip_radiotap(packet)?),
+        LINKTYPE_BLUETOOTH_LE_LL => from_ble_ll(packet, false),
+        LINKTYPE_BLUETOOTH_LE_LL_WITH_PHDR => from_ble_ll_with_phdr(packet),
+        _ => Err(DecodeError::MissingRemoteId),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Packet<'a> {
+    link_type: u32,
+    timestamp: DateTime<Utc>,
+    data: &'a [u8],
+}
+
+#[derive(Debug, Clone)]
+enum Packets<'a> {
+    Pcap(PcapPackets<'a>),
+    Pcapng(PcapngPackets<'a>),
+}
+
+impl<'a> Iterator for Packets<'a> {
+    type Item = Result<Packet<'a>, CaptureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Packets::Pcap(packets) => packets.next(),
+            Packets::Pcapng(packets) => packets.next(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ByteOrder {
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    fn u16_at(self, data: &[u8], offset: usize) -> Result<u16, CaptureError> {
+        let Some(&[a, b]) = data.get(offset..offset + 2) else {
+            return Err(CaptureError::Truncated);
+        };
+        Ok(match self {
+            ByteOrder::Little => u16::from_le_bytes([a, b]),
+            ByteOrder::Big => u16::from_be_bytes([a, b]),
+        })
+    }
+
+    fn u32_at(self, data: &[u8], offset: usize) -> Result<u32, CaptureError> {
+        let Some(&[a, b, c, d]) = data.get(offset..offset + 4) else {
+            return Err(CaptureError::Truncated);
+        };
+        Ok(match self {
+            ByteOrder::Little => u32::from_le_bytes([a, b, c, d]),
+            ByteOrder::Big => u32::from_be_bytes([a, b, c, d]),
+        })
+    }
+
+    fn u64_at(self, data: &[u8], offset: usize) -> Result<u64, CaptureError> {
+        let high = self.u32_at(data, offset)?;
+        let low = self.u32_at(data, offset + 4)?;
+        Ok(match self {
+            ByteOrder::Little => (u64::from(low) << 32) | u64::from(high),
+            ByteOrder::Big => (u64::from(high) << 32) | u64::from(low),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PcapPackets<'a> {
+    order: ByteOrder,
+    link_type: u32,
+    units_per_second: u64,
+    records: &'a [u8],
+}
+
+impl<'a> PcapPackets<'a> {
+    fn new(capture: &'a [u8]) -> Result<Self, CaptureError> {
+        let (order, units_per_second) = [ByteOrder::Little, ByteOrder::Big]
+            .into_iter()
+            .find_map(|order| match order.u32_at(capture, 0).ok()? {
+                PCAP_MAGIC_MICROS => Some((order, 1_000_000)),
+                PCAP_MAGIC_NANOS => Some((order, 1_000_000_000)),
+                _ => None,
+            })
+            .ok_or(CaptureError::UnknownFormat)?;
+        // the upper bits of the link type carry FCS information
+        let link_type = order.u32_at(capture, 20)? & 0xFFFF;
+
+        Ok(PcapPackets {
+            order,
+            link_type,
+            units_per_second,
+            records: &capture[PCAP_HEADER_BYTE_SIZE..],
+        })
+    }
+
+    fn next_record(&mut self) -> Result<Packet<'a>, CaptureError> {
+        let seconds = self.order.u32_at(self.records, 0)?;
+        let fraction = self.order.u32_at(self.records, 4)?;
+        let len = self.order.u32_at(self.records, 8)? as usize;
+
+        // the length is read from the file and may overflow on 32 bit targets
+        let end = PCAP_RECORD_HEADER_BYTE_SIZE
+            .checked_add(len)
+            .ok_or(CaptureError::Truncated)?;
+        let data = self
+            .records
+            .get(PCAP_RECORD_HEADER_BYTE_SIZE..end)
+            .ok_or(CaptureError::Truncated)?;
+        self.records = &self.records[end..];
+
+        if u64::from(fraction) >= self.units_per_second {
+            return Err(CaptureError::InvalidTimestamp);
+        }
+        let ticks = u64::from(seconds) * self.units_per_second + u64::from(fraction);
+        Ok(Packet {
+            link_type: self.link_type,
+            timestamp: timestamp(ticks, self.units_per_second, 0)?,
+            data,
+        })
+    }
+}
+
+impl<'a> Iterator for PcapPackets<'a> {
+    type Item = Result<Packet<'a>, CaptureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.records.is_empty() {
+            return None;
+        }
+        let packet = self.next_record();
+        if packet.is_err() {
+            self.records = &[];
+        }
+        Some(packet)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Interface {
+    link_type: u32,
+    units_per_second: u64,
+    /// Seconds added to all timestamps
+    offset: i64,
+}
+
+impl Interface {
+    fn parse(order: ByteOrder, body: &[u8]) -> Result<Self, CaptureError> {
+        let mut interface = Interface {
+            link_type: order.u16_at(body, 0)?.into(),
+            units_per_second: 1_000_000,
+            offset: 0,
+        };
+
+        let mut options = body.get(8..).ok_or(CaptureError::InvalidBlock)?;
+        while options.len() >= 4 {
+            let code = order.u16_at(options, 0)?;
+            let len = order.u16_at(options, 2)? as usize;
+            let value = options.get(4..4 + len).ok_or(CaptureError::InvalidBlock)?;
+            match (code, value) {
+                (OPTION_END_OF_OPT, _) => break,
+                (OPTION_IF_TSRESOL, &[resolution]) => {
+                    interface.units_per_second = units_per_second(resolution)?
+                }
+                (OPTION_IF_TSOFFSET, _) => interface.offset = order.u64_at(value, 0)? as i64,
+                _ => {}
+            }
+            // option values are padded to 32 bits
+            options = options.get(4 + len.next_multiple_of(4)..).unwrap_or(&[]);
+        }
+
+        Ok(interface)
+    }
+}
+
+/// The resolution is a negative power of 10, or of 2 if the most significant bit is set
+fn units_per_second(resolution: u8) -> Result<u64, CaptureError> {
+    let exponent = u32::from(resolution & 0x7F);
+    let base: u64 = if resolution & 0x80 == 0 { 10 } else { 2 };
+    base.checked_pow(exponent)
+        .ok_or(CaptureError::InvalidTimestamp)
+}
+
+#[derive(Debug, Clone)]
+struct PcapngPackets<'a> {
+    order: ByteOrder,
+    interfaces: Vec<Interface>,
+    blocks: &'a [u8],
+}
+
+impl<'a> PcapngPackets<'a> {
+    fn new(capture: &'a [u8]) -> Self {
+        PcapngPackets {
+            // replaced by the byte order of the section header block
+            order: ByteOrder::Little,
+            interfaces: Vec::new(),
+            blocks: capture,
+        }
+    }
+
+    /// The next block, if it is a packet
+    fn next_block(&mut self) -> Result<Option<Packet<'a>>, CaptureError> {
+        // a new section may change the byte order and describes its own interfaces
+        if self.blocks.starts_with(&PCAPNG_SECTION_HEADER) {
+            self.order = [ByteOrder::Little, ByteOrder::Big]
+                .into_iter()
+                .find(|order| order.u32_at(self.blocks, 8) == Ok(PCAPNG_BYTE_ORDER_MAGIC))
+                .ok_or(CaptureError::InvalidBlock)?;
+            self.interfaces.clear();
+        }
+
+        let block_type = self.order.u32_at(self.blocks, 0)?;
+        let len = self.order.u32_at(self.blocks, 4)? as usize;
+        if len < PCAPNG_BLOCK_OVERHEAD_BYTE_SIZE || !len.is_multiple_of(4) {
+            return Err(CaptureError::InvalidBlock);
+        }
+        let block = self.blocks.get(..len).ok_or(CaptureError::Truncated)?;
+        self.blocks = &self.blocks[len..];
+        let body = &block[8..len - 4];
+
+        match block_type {
+            PCAPNG_INTERFACE_DESCRIPTION => {
+                self.interfaces.push(Interface::parse(self.order, body)?);
+                Ok(None)
+            }
+            PCAPNG_ENHANCED_PACKET => self.enhanced_packet(body).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn enhanced_packet(&self, body: &'a [u8]) -> Result<Packet<'a>, CaptureError> {
+        let interface_id = self.order.u32_at(body, 0)?;
+        let high = self.order.u32_at(body, 4)?;
+        let low = self.order.u32_at(body, 8)?;
+        let len = self.order.u32_at(body, 12)? as usize;
+
+        let interface = self
+            .interfaces
+            .get(interface_id as usize)
+            .ok_or(CaptureError::UnknownInterface(interface_id))?;
+        let end = PCAPNG_ENHANCED_PACKET_HEADER_BYTE_SIZE
+            .checked_add(len)
+            .ok_or(CaptureError::InvalidBlock)?;
+        let data = body
+            .get(PCAPNG_ENHANCED_PACKET_HEADER_BYTE_SIZE..end)
+            .ok_or(CaptureError::InvalidBlock)?;
+
+        let ticks = (u64::from(high) << 32) | u64::from(low);
+        Ok(Packet {
+            link_type: interface.link_type,
+            timestamp: timestamp(ticks, interface.units_per_second, interface.offset)?,
+            data,
+        })
+    }
+}
+
+impl<'a> Iterator for PcapngPackets<'a> {
+    type Item = Result<Packet<'a>, CaptureError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.blocks.is_empty() {
+            match self.next_block() {
+                Ok(Some(packet)) => return Some(Ok(packet)),
+                Ok(None) => {}
+                Err(e) => {
+                    self.blocks = &[];
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+fn timestamp(
+    ticks: u64,
+    units_per_second: u64,
+    offset: i64,
+) -> Result<DateTime<Utc>, CaptureError> {
+    let seconds = i64::try_from(ticks / units_per_second)
+        .ok()
+        .and_then(|seconds| seconds.checked_add(offset))
+        .ok_or(CaptureError::InvalidTimestamp)?;
+    let nanos = u128::from(ticks % units_per_second) * 1_000_000_000 / u128::from(units_per_second);
+
+    DateTime::from_timestamp(seconds, nanos as u32).ok_or(CaptureError::InvalidTimestamp)
+}
+
+/// Header of the given size and the rest of the packet
+fn split_header(packet: &[u8], len: usize) -> Result<(&[u8], &[u8]), DecodeError> {
+    packet
+        .split_at_checked(len)
+        .ok_or(DecodeError::BufferTooShort {
+            expected: len,
+            actual: packet.len(),
+        })
+}
+
+/// The 802.11 frame following the radiotap header, without FCS
+fn strip_radiotap(packet: &[u8]) -> Result<&[u8], DecodeError> {
+    let [_version, _pad, len_lo, len_hi, ..] = *packet else {
+        return Err(DecodeError::BufferTooShort {
+            expected: 4,
+            actual: packet.len(),
+        });
+    };
+    let (header, frame) = split_header(packet, u16::from_le_bytes([len_lo, len_hi]) as usize)?;
+
+    if radiotap_flags(header) & RADIOTAP_FLAG_FCS == 0 {
+        return Ok(frame);
+    }
+    Ok(&frame[..frame.len().saturating_sub(FCS_BYTE_SIZE)])
+}
+
+/// The flags field of a radiotap header, zero if it is not present
+fn radiotap_flags(header: &[u8]) -> u8 {
+    let present = |offset: usize| {
+        header
+            .get(offset..offset + 4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+    };
+    let Some(fields) = present(4) else {
+        return 0;
+    };
+
+    // further present words follow as long as the extension bit is set
+    let mut offset = 4;
+    while let Some(word) = present(offset) {
+        offset += 4;
+        if word & RADIOTAP_PRESENT_EXT == 0 {
+            break;
+        }
+    }
+
+    if fields & RADIOTAP_PRESENT_FLAGS == 0 {
+        return 0;
+    }
+    // the TSFT field precedes the flags and is aligned to 8 bytes
+    if fields & RADIOTAP_PRESENT_TSFT != 0 {
+        offset = offset.next_multiple_of(8) + 8;
+    }
+    header.get(offset).copied().unwrap_or(0)
+}
+
+fn from_ieee802_11(
+    frame: &[u8],
+) -> Result<([u8; ADDRESS_BYTE_SIZE], ServiceDataFrame), DecodeError> {
+    let (header, body) = split_header(frame, MAC_HEADER_BYTE_SIZE)?;
+    // only management frames carry Remote ID
+    if header[0] & 0x0C != 0 {
+        return Err(DecodeError::MissingRemoteId);
+    }
+    // the order bit of management frames signals an HT control field
+    let body = if header[1] & 0x80 != 0 {
+        split_header(body, 4)?.1
+    } else {
+        body
+    };
+
+    let frame = match header[0] >> 4 {
+        MAC_SUBTYPE_BEACON => wifi_beacon::from_beacon_body(body)?,
+        MAC_SUBTYPE_ACTION | MAC_SUBTYPE_ACTION_NO_ACK => {
+            wifi_nan::from_service_discovery_frame(body)?
+        }
+        _ => return Err(DecodeError::MissingRemoteId),
+    };
+
+    let mut address = [0; ADDRESS_BYTE_SIZE];
+    address.copy_from_slice(&header[10..16]);
+    Ok((address, frame))
+}
+
+fn from_ble_ll_with_phdr(
+    packet: &[u8],
+) -> Result<([u8; ADDRESS_BYTE_SIZE], ServiceDataFrame), DecodeError> {
+    let (phdr, packet) = split_header(packet, PHDR_BYTE_SIZE)?;
+    let flags = u16::from_le_bytes([phdr[8], phdr[9]]);
+    if flags & PHDR_CRC_CHECKED != 0 && flags & PHDR_CRC_VALID == 0 {
+        return Err(DecodeError::InvalidCrc);
+    }
+    from_ble_ll(packet, flags & PHDR_PHY_MASK == PHDR_PHY_CODED)
+}
+
+fn from_ble_ll(
+    packet: &[u8],
+    coded: bool,
+) -> Result<([u8; ADDRESS_BYTE_SIZE], ServiceDataFrame), DecodeError> {
+    let (access_address, pdu) = split_header(packet, 4)?;
+    if *access_address != ADVERTISING_ACCESS_ADDRESS.to_le_bytes() {
+        return Err(DecodeError::MissingRemoteId);
+    }
+    // the coding indicator precedes the PDU on the coded PHY
+    let pdu = if coded { split_header(pdu, 1)?.1 } else { pdu };
+
+    let (header, rest) = split_header(pdu, 2)?;
+    // the CRC follows the payload
+    let (payload, _) = split_header(rest, header[1] as usize)?;
+
+    let (advertiser, advertising_data) = match header[0] & 0x0F {
+        PDU_ADV_IND | PDU_ADV_NONCONN_IND | PDU_SCAN_RSP | PDU_ADV_SCAN_IND => {
+            split_header(payload, ADDRESS_BYTE_SIZE)?
+        }
+        PDU_ADV_EXT_IND => split_extended_payload(payload)?,
+        _ => return Err(DecodeError::MissingRemoteId),
+    };
+
+    // addresses are transmitted least significant byte first
+    let mut address = [0; ADDRESS_BYTE_SIZE];
+    address.copy_from_slice(advertiser);
+    address.reverse();
+
+    Ok((address, ble::from_advertising_payload(advertising_data)?))
+}
+
+/// Advertiser address and advertising data of an extended advertising payload
+fn split_extended_payload(payload: &[u8]) -> Result<(&[u8], &[u8]), DecodeError> {
+    let (&header_len, rest) = payload.split_first().ok_or(DecodeError::BufferTooShort {
+        expected: 1,
+        actual: 0,
+    })?;
+    // the upper bits are the advertising mode
+    let (header, advertising_data) = split_header(rest, (header_len & 0x3F) as usize)?;
+
+    // the advertiser address is the first field of the extended header, if present
+    match header.split_first() {
+        Some((&flags, fields)) if flags & EXTENDED_HEADER_ADV_A != 0 => {
+            Ok((split_header(fields, ADDRESS_BYTE_SIZE)?.0, advertising_data))
+        }
+        // e.g. AUX_CHAIN_IND
+        _ => Err(DecodeError::MissingRemoteId),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::basic_id::BasicId;
+    use crate::data::location::Location;
+    use crate::data::RemoteIDMessage;
+    use crate::transport::ble::{to_extended_payload, to_legacy_payload};
+    use crate::transport::wifi_beacon::{to_vendor_element, BEACON_FIXED_FIELDS_BYTE_SIZE};
+    use crate::transport::wifi_nan::to_service_discovery_frame;
+
+    const ADDRESS: [u8; 6] = [0xC0, 0x01, 0x02, 0x03, 0x04, 0x05];
+
+    fn basic_id() -> RemoteIDMessage {
+        RemoteIDMessage::BasicID(
+            BasicId::builder()
+                .serial_number("1596F359746167260749")
+                .build()
+                .unwrap(),
+        )
+    }
+
+    fn message_pack() -> RemoteIDMessage {
+        RemoteIDMessage::MessagePack(alloc::vec![
+            basic_id(),
+            RemoteIDMessage::Location(Location::builder().build().unwrap()),
+        ])
+    }
+
+    fn ble_ll(pdu_type: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = ADVERTISING_ACCESS_ADDRESS.to_le_bytes().to_vec();
+        packet.extend_from_slice(&[pdu_type, payload.len() as u8]);
+        packet.extend_from_slice(payload);
+        // CRC
+        packet.extend_from_slice(&[0xAA, 0xBB, 0xCC]);
+        packet
+    }
+
+    fn advertiser_address() -> [u8; 6] {
+        let mut address = ADDRESS;
+        address.reverse();
+        address
+    }
+
+    fn mac_header(subtype: u8) -> Vec<u8> {
+        let mut header = alloc::vec![subtype << 4, 0x00, 0x00, 0x00];
+        header.extend_from_slice(&[0xFF; 6]);
+        header.extend_from_slice(&ADDRESS);
+        header.extend_from_slice(&ADDRESS);
+        header.extend_from_slice(&[0x00, 0x00]);
+        header
+    }
+
+    fn pcap(link_type: u32, packets: &[(u32, u32, &[u8])]) -> Vec<u8> {
+        let mut capture = PCAP_MAGIC_MICROS.to_le_bytes().to_vec();
+        capture.extend_from_slice(&[2, 0, 4, 0]);
+        capture.extend_from_slice(&[0; 8]);
+        capture.extend_from_slice(&65535u32.to_le_bytes());
+        capture.extend_from_slice(&link_type.to_le_bytes());
+        for (seconds, micros, data) in packets {
+            capture.extend_from_slice(&seconds.to_le_bytes());
+            capture.extend_from_slice(&micros.to_le_bytes());
+            capture.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            capture.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            capture.extend_from_slice(data);
+        }
+        capture
+    }
+
+    fn pcapng_block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let len = (PCAPNG_BLOCK_OVERHEAD_BYTE_SIZE + body.len().next_multiple_of(4)) as u32;
+        let mut block = block_type.to_be_bytes().to_vec();
+        block.extend_from_slice(&len.to_be_bytes());
+        block.extend_from_slice(body);
+        block.resize(len as usize - 4, 0);
+        block.extend_from_slice(&len.to_be_bytes());
+        block
+    }
+
+    fn enhanced_packet(interface_id: u32, ticks: u64, data: &[u8]) -> Vec<u8> {
+        let mut body = interface_id.to_be_bytes().to_vec();
+        body.extend_from_slice(&((ticks >> 32) as u32).to_be_bytes());
+        body.extend_from_slice(&(ticks as u32).to_be_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        body.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        body.extend_from_slice(data);
+        pcapng_block(PCAPNG_ENHANCED_PACKET, &body)
+    }
+
+    #[test]
+    fn read_ble_pcap() {
+        let mut legacy = advertiser_address().to_vec();
+        legacy.extend_from_slice(&to_legacy_payload(&basic_id(), 4).unwrap());
+        // scan request, without advertising data
+        let scan_request = ble_ll(0x3, &[0; 12]);
+
+        let capture = pcap(
+            LINKTYPE_BLUETOOTH_LE_LL,
+            &[
+                (1720101954, 0, &scan_request),
+                (1720101954, 250_000, &ble_ll(PDU_ADV_NONCONN_IND, &legacy)),
+            ],
+        );
+
+        let captured = CaptureReader::new(&capture)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(1, captured.len());
+        assert_eq!(
+            DateTime::from_timestamp(1720101954, 250_000_000).unwrap(),
+            captured[0].timestamp
+        );
+        assert_eq!(ADDRESS, captured[0].address);
+        assert_eq!(4, captured[0].frame.counter);
+        assert_eq!(basic_id(), captured[0].frame.message);
+    }
+
+    #[test]
+    fn read_coded_phy_with_phdr() {
+        // extended header with the advertiser address only
+        let mut extended = alloc::vec![7, EXTENDED_HEADER_ADV_A];
+        extended.extend_from_slice(&advertiser_address());
+        extended.extend_from_slice(&to_extended_payload(&message_pack(), 9).unwrap());
+
+        let mut packet = alloc::vec![37, 0xC4, 0xA0, 0, 0xD6, 0xBE, 0x89, 0x8E];
+        packet
+            .extend_from_slice(&(PHDR_CRC_CHECKED | PHDR_CRC_VALID | PHDR_PHY_CODED).to_le_bytes());
+        let mut ll = ble_ll(PDU_ADV_EXT_IND, &extended);
+        // coding indicator
+        ll.insert(4, 0x00);
+        packet.extend_from_slice(&ll);
+
+        let (address, frame) = from_packet(LINKTYPE_BLUETOOTH_LE_LL_WITH_PHDR, &packet).unwrap();
+        assert_eq!(ADDRESS, address);
+        assert_eq!(message_pack(), frame.message);
+
+        // CRC error
+        packet[8] = 0x00;
+        packet[9] = ((PHDR_CRC_CHECKED | PHDR_PHY_CODED) >> 8) as u8;
+        assert_eq!(
+            Err(DecodeError::InvalidCrc),
+            from_packet(LINKTYPE_BLUETOOTH_LE_LL_WITH_PHDR, &packet)
+        );
+    }
+
+    #[test]
+    fn read_wifi_pcapng() {
+        // radiotap header with flags, FCS at the end
+        let mut beacon = alloc::vec![0, 0, 10, 0, 0x02, 0, 0, 0, RADIOTAP_FLAG_FCS, 0];
+        beacon.extend_from_slice(&mac_header(MAC_SUBTYPE_BEACON));
+        beacon.extend_from_slice(&[0; BEACON_FIXED_FIELDS_BYTE_SIZE]);
+        beacon.extend_from_slice(&to_vendor_element(&message_pack(), 2).unwrap());
+        beacon.extend_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+
+        let mut nan = mac_header(MAC_SUBTYPE_ACTION);
+        nan.extend_from_slice(&to_service_discovery_frame(&message_pack(), 3).unwrap());
+
+        let mut section = PCAPNG_BYTE_ORDER_MAGIC.to_be_bytes().to_vec();
+        section.extend_from_slice(&[0, 1, 0, 0]);
+        section.extend_from_slice(&[0xFF; 8]);
+
+        // interfaces with nanosecond resolution and the default microsecond resolution
+        let mut radiotap = alloc::vec![0, LINKTYPE_IEEE802_11_RADIOTAP as u8, 0, 0, 0, 0, 0, 0];
+        radiotap.extend_from_slice(&[0, OPTION_IF_TSRESOL as u8, 0, 1, 9, 0, 0, 0]);
+        radiotap.extend_from_slice(&[0, 0, 0, 0]);
+        let ieee802_11 = [0, LINKTYPE_IEEE802_11 as u8, 0, 0, 0, 0, 0, 0];
+
+        let mut capture = pcapng_block(u32::from_be_bytes(PCAPNG_SECTION_HEADER), &section);
+        capture.extend(pcapng_block(PCAPNG_INTERFACE_DESCRIPTION, &radiotap));
+        capture.extend(pcapng_block(PCAPNG_INTERFACE_DESCRIPTION, &ieee802_11));
+        // name resolution block
+        capture.extend(pcapng_block(0x0000_0004, &[0; 4]));
+        capture.extend(enhanced_packet(0, 1_720_101_954_000_000_001, &beacon));
+        capture.extend(enhanced_packet(1, 1_720_101_955_000_000, &nan));
+
+        let captured = CaptureReader::new(&capture)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(2, captured.len());
+
+        assert_eq!(
+            DateTime::from_timestamp(1720101954, 1).unwrap(),
+            captured[0].timestamp
+        );
+        assert_eq!(ADDRESS, captured[0].address);
+        assert_eq!(2, captured[0].frame.counter);
+        assert_eq!(message_pack(), captured[0].frame.message);
+
+        assert_eq!(
+            DateTime::from_timestamp(1720101955, 0).unwrap(),
+            captured[1].timestamp
+        );
+        assert_eq!(3, captured[1].frame.counter);
+        assert_eq!(message_pack(), captured[1].frame.message);
+    }
+
+    #[test]
+    fn reject_malformed_captures() {
+        assert_eq!(
+            Some(CaptureError::UnknownFormat),
+            CaptureReader::new(&[0; 24]).err()
+        );
+
+        let mut capture = pcap(LINKTYPE_BLUETOOTH_LE_LL, &[(0, 0, &[0; 8])]);
+        capture.truncate(capture.len() - 1);
+        let mut reader = CaptureReader::new(&capture).unwrap();
+        assert_eq!(Some(Err(CaptureError::Truncated)), reader.next());
+        assert_eq!(None, reader.next());
+
+        // lengths that overflow on 32 bit targets
+        let mut capture = pcap(LINKTYPE_BLUETOOTH_LE_LL, &[(0, 0, &[0; 8])]);
+        capture[PCAP_HEADER_BYTE_SIZE + 8..PCAP_HEADER_BYTE_SIZE + 12]
+            .copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut reader = CaptureReader::new(&capture).unwrap();
+        assert_eq!(Some(Err(CaptureError::Truncated)), reader.next());
+
+        let mut section = PCAPNG_BYTE_ORDER_MAGIC.to_be_bytes().to_vec();
+        section.extend_from_slice(&[0; 12]);
+        let mut capture = pcapng_block(u32::from_be_bytes(PCAPNG_SECTION_HEADER), &section);
+        capture.extend(enhanced_packet(0, 0, &[0; 8]));
+        assert_eq!(
+            Some(Err(CaptureError::UnknownInterface(0))),
+            CaptureReader::new(&capture).unwrap().next()
+        );
+
+        let interface = [0, LINKTYPE_BLUETOOTH_LE_LL as u8, 0, 0, 0, 0, 0, 0];
+        let mut capture = pcapng_block(u32::from_be_bytes(PCAPNG_SECTION_HEADER), &section);
+        capture.extend(pcapng_block(PCAPNG_INTERFACE_DESCRIPTION, &interface));
+        let mut packet = enhanced_packet(0, 0, &[0; 8]);
+        packet[20..24].copy_from_slice(&u32::MAX.to_be_bytes());
+        capture.extend(packet);
+        let mut reader = CaptureReader::new(&capture).unwrap();
+        assert_eq!(Some(Err(CaptureError::InvalidBlock)), reader.next());
+    }
+}
@@ -1,230 +1,219 @@
-use core::time::Duration;
+use alloc::vec::Vec;
 
-use basic_id::{BasicId, IdType, UAType};
-use chrono::DateTime;
-use location::{
-    HeightType, HorizontalAccuracy, Location, OperationalStatus, SpeedAccuracy, VerticalAccuracy,
+use authentication::{
+    AuthType, Authentication, AuthenticationPage, FIRST_PAGE_DATA_BYTE_SIZE, PAGE_DATA_BYTE_SIZE,
 };
-use operator_id::{OperatorId, OperatorIdType};
-use system::{ClassificationType, OperatorLocationType, System};
+use chrono::DateTime;
 
 extern crate std;
-use crate::data::system::{UaCategory, UaClass, UaClassification};
 use crate::{data::*, get_bits, get_bytes};
-use crate::{MAX_ID_BYTE_SIZE, OPEN_DRONE_ID_AD_CODE};
-
-use super::{copy_to_id, MessageType};
+use crate::{OPEN_DRONE_ID_AD_CODE, REMOTE_ID_EPOCH};
+
+use super::view::{BasicIdView, LocationView, OperatorIdView, SelfIdView, SystemView};
+use super::{copy_to_array, MessageType, ProtocolVersion, ServiceDataFrame};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DecodeError {
+    /// The buffer is shorter than the message requires
+    BufferTooShort { expected: usize, actual: usize },
+    /// The service data does not start with the Open Drone ID application code
+    InvalidAdCode(u8),
+    /// The message type is not defined by the specification
+    UnsupportedMessageType(u8),
+    /// The timestamp is outside of the range allowed by the specification
+    InvalidTimestamp,
+    /// The protocol version is reserved by the specification
+    InvalidProtocolVersion(u8),
+    /// The message pack header is malformed or the pack contains another message pack
+    InvalidMessagePack,
+    /// A view was created over a message of another type
+    UnexpectedMessageType(u8),
+    /// An element or attribute of a Wi-Fi frame is malformed
+    InvalidElement,
+    /// The frame does not carry Remote ID messages
+    MissingRemoteId,
+    /// The capturing device reports a CRC error for the packet
+    InvalidCrc,
+    /// Page 0 announces more than 16 pages, or a length that does not need exactly its pages
+    InvalidAuthenticationLength,
+}
 
-pub fn from_service_data(data: &[u8]) -> Option<RemoteIDMessage> {
-    let first_byte = data[0];
-    if first_byte != OPEN_DRONE_ID_AD_CODE {
-        // all RemoteID Messages start with this byte?
-        return None;
+impl core::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DecodeError::BufferTooShort { expected, actual } => {
+                write!(
+                    f,
+                    "buffer too short: expected {expected} bytes, got {actual}"
+                )
+            }
+            DecodeError::InvalidAdCode(code) => write!(f, "invalid application code {code:#04x}"),
+            DecodeError::UnsupportedMessageType(message_type) => {
+                write!(f, "unsupported message type {message_type:#x}")
+            }
+            DecodeError::InvalidTimestamp => write!(f, "invalid timestamp"),
+            DecodeError::InvalidProtocolVersion(version) => {
+                write!(f, "invalid protocol version {version}")
+            }
+            DecodeError::InvalidMessagePack => write!(f, "invalid message pack"),
+            DecodeError::UnexpectedMessageType(message_type) => {
+                write!(f, "unexpected message type {message_type:#x}")
+            }
+            DecodeError::InvalidElement => write!(f, "invalid element"),
+            DecodeError::MissingRemoteId => write!(f, "no remote id in frame"),
+            DecodeError::InvalidCrc => write!(f, "invalid crc"),
+            DecodeError::InvalidAuthenticationLength => {
+                write!(f, "invalid authentication length")
+            }
+        }
     }
-
-    let _message_counter = data[1];
-
-    from_message_buffer(&data[2..])
 }
 
-pub fn from_message_buffer(data: &[u8]) -> Option<RemoteIDMessage> {
-    // protocol version, reserved for private use
-    let _version = get_bits!(data[0], 3..0);
-
-    match MessageType::from(get_bits!(data[0], 7..4)) {
-        MessageType::BasicId => parse_basic_id(data),
-        MessageType::Location => parse_location(data),
-        MessageType::OperatorId => parse_operator_id(data),
-        MessageType::System => parse_system(data),
-
-        // we have no examples for these yet
-        MessageType::Selfid => todo!(),
-        MessageType::Auth => todo!(),
-        MessageType::MessagePack => todo!(),
+impl core::error::Error for DecodeError {}
 
-        MessageType::Invalid => None,
+fn ensure_len(buffer: &[u8], expected: usize) -> Result<(), DecodeError> {
+    if buffer.len() < expected {
+        return Err(DecodeError::BufferTooShort {
+            expected,
+            actual: buffer.len(),
+        });
     }
+    Ok(())
 }
 
-fn parse_basic_id(buffer: &[u8]) -> Option<RemoteIDMessage> {
-    let id_type = IdType::from(get_bits!(buffer[1], 7..4));
-    let ua_type = UAType::from(get_bits!(buffer[1], 3..0));
-
-    let uas_id = copy_to_id(get_bytes!(buffer, 2, crate::MAX_ID_BYTE_SIZE));
-
-    Some(RemoteIDMessage::BasicID(BasicId {
-        id_type,
-        ua_type,
-        uas_id,
-    }))
+pub fn from_service_data(data: &[u8]) -> Result<RemoteIDMessage, DecodeError> {
+    from_service_data_frame(data).map(|frame| frame.message)
 }
 
-fn parse_operator_id(buffer: &[u8]) -> Option<RemoteIDMessage> {
-    // Operator ID Type
-    let id_type = OperatorIdType::from(buffer[1]);
-
-    // Operator ID
-    let operator_id = copy_to_id(get_bytes!(buffer, 2, MAX_ID_BYTE_SIZE));
+/// Like [from_service_data], but keeps the message counter
+pub fn from_service_data_frame(data: &[u8]) -> Result<ServiceDataFrame, DecodeError> {
+    ensure_len(data, 2)?;
 
-    let _reserved = get_bytes!(buffer, MAX_ID_BYTE_SIZE + 2, 3);
+    let first_byte = data[0];
+    if first_byte != OPEN_DRONE_ID_AD_CODE {
+        // all RemoteID Messages start with this byte?
+        return Err(DecodeError::InvalidAdCode(first_byte));
+    }
 
-    Some(RemoteIDMessage::OperatorId(OperatorId {
-        id_type,
-        operator_id,
-    }))
+    Ok(ServiceDataFrame {
+        counter: data[1],
+        message: from_message_buffer(&data[2..])?,
+    })
 }
 
-fn parse_system(buffer: &[u8]) -> Option<RemoteIDMessage> {
-    let flags = buffer[1];
-    // Reserved: Bits [7..5]
-    let _reserved = get_bits!(flags, 7..5);
-    // Classification Type: Bits [4..2]
-    let classification_type = ClassificationType::from(get_bits!(flags, 4..2));
-    // Operator Location/Altitude source type: Bits [1–0]
-    let operator_location_type = OperatorLocationType::from(get_bits!(flags, 1..0));
-
-    // Operator Latitude
-    //    Latitude of Remote Pilot
-    let operator_latidute =
-        u32::from_le_bytes(get_bytes!(buffer, 2, 4)) as f32 / f32::powf(10., 7.);
-
-    // Operator Longitude
-    //   Longitude of Remote Pilot
-    let operator_longitude =
-        u32::from_le_bytes(get_bytes!(buffer, 6, 4)) as f32 / f32::powf(10., 7.);
-
-    // Area Count
-    //   Number of aircraft in Area, group or formation (default 1)
-    let area_count = u16::from_le_bytes(get_bytes!(buffer, 10, 2));
-
-    // Area Radius
-    //   Radius of cylindrical area of group or formation * 10 m (default 0)
-    //   centered on Location/Vector Message position
-    let area_radius = get_bytes!(buffer, 12, 1) as f32 * 10.;
-
-    // Area Ceiling
-    //   Group operations ceiling WGS-84 HAE (Altitude + 1000 m)/0.5
-    let area_ceiling = u16::from_le_bytes(get_bytes!(buffer, 13, 2)) as f32 / 2. - 1000.;
-
-    // Area Floor
-    //   Group operations floor WGS-84 HAE (Altitude + 1000 m)/0.5
-    let area_floor = u16::from_le_bytes(get_bytes!(buffer, 15, 2)) as f32 / 2. - 1000.;
-
-    // TODO UA Classification
-    let ua_classification = if classification_type == ClassificationType::EuropeanUnion {
-        UaClassification {
-            category: UaCategory::from(buffer[17] >> 3),
-            class: UaClass::from(get_bits!(buffer[17], 0..3)),
-        }
-    } else {
-        UaClassification::undefined()
-    };
+/// Peek at the protocol version of a message
+pub fn protocol_version(data: &[u8]) -> Result<ProtocolVersion, DecodeError> {
+    ensure_len(data, 1)?;
 
-    // Operator Altitude
-    let operator_altitude = u16::from_le_bytes(get_bytes!(buffer, 18, 2)) as f32 / 2. - 1000.;
-
-    // Timestamp
-    let unix_secs = u32::from_le_bytes(get_bytes!(buffer, 20, 4));
-    let timestamp = DateTime::from_timestamp(unix_secs as i64 + 1546300800, 0)?;
-
-    // Reserved
-    let _reserved = get_bytes!(buffer, 24, 1);
-
-    Some(RemoteIDMessage::System(System {
-        classification_type,
-        operator_location_type,
-        operator_latidute,
-        operator_longitude,
-        operator_altitude,
-        area_count,
-        area_radius,
-        area_floor,
-        area_ceiling,
-        ua_classification,
-        timestamp,
-    }))
+    ProtocolVersion::try_from(get_bits!(data[0], 3..0)).map_err(DecodeError::InvalidProtocolVersion)
 }
 
-fn parse_location(buffer: &[u8]) -> Option<RemoteIDMessage> {
-    // Status, Flags
-    let status_flags = get_bytes!(buffer, 1, 1);
+/// Peek at the type of a message without decoding it
+pub fn message_type(data: &[u8]) -> Result<MessageType, DecodeError> {
+    ensure_len(data, 1)?;
 
-    let operational_status = OperationalStatus::from(get_bits!(status_flags, 7..4));
-    let _reserved = get_bits!(status_flags, 3..3);
-    let height_type = HeightType::from(get_bits!(status_flags, 2..2));
-    let ew_direction_segment = get_bits!(status_flags, 1..1);
-    let speed_multiplier = get_bits!(status_flags, 0..0);
-
-    // Track Direction
-    let track_direction = get_bytes!(buffer, 2, 1);
-    let track_direction = if ew_direction_segment > 0 {
-        track_direction as u16 + 180
-    } else {
-        track_direction as u16
-    };
-
-    // Speed
-    let speed = get_bytes!(buffer, 3, 1);
-    let speed = if speed_multiplier == 0 {
-        speed as f32 * 0.25
-    } else {
-        (speed as f32 + 255. * 0.25) * 0.75
-    };
-
-    // Vertical Speed
-    let vertical_speed = get_bytes!(buffer, 4, 1);
-    let vertical_speed = vertical_speed as f32 * 0.5;
+    let message_type = get_bits!(data[0], 7..4);
+    match MessageType::from(message_type) {
+        MessageType::Invalid => Err(DecodeError::UnsupportedMessageType(message_type)),
+        message_type => Ok(message_type),
+    }
+}
 
-    // Latitude
-    let latidute = u32::from_le_bytes(get_bytes!(buffer, 5, 4)) as f32 / f32::powf(10., 7.);
+pub fn from_message_buffer(data: &[u8]) -> Result<RemoteIDMessage, DecodeError> {
+    protocol_version(data)?;
 
-    // Longitude
-    let longitude = u32::from_le_bytes(get_bytes!(buffer, 9, 4)) as f32 / f32::powf(10., 7.);
+    let message_type = message_type(data)?;
+    match message_type {
+        MessageType::MessagePack => ensure_len(data, message_pack::HEADER_BYTE_SIZE)?,
+        _ => ensure_len(data, message_pack::MESSAGE_BYTE_SIZE)?,
+    }
 
-    // Pressure Altitude
-    let pressure_altitude = u16::from_le_bytes(get_bytes!(buffer, 13, 2)) as f32 / 2.0 - 1000.;
+    match message_type {
+        MessageType::BasicId => Ok(RemoteIDMessage::BasicID(BasicIdView::new(data)?.into())),
+        MessageType::Location => Ok(RemoteIDMessage::Location(
+            LocationView::new(data)?.try_into()?,
+        )),
+        MessageType::Auth => parse_authentication(data),
+        MessageType::OperatorId => Ok(RemoteIDMessage::OperatorId(
+            OperatorIdView::new(data)?.into(),
+        )),
+        MessageType::Selfid => Ok(RemoteIDMessage::SelfId(SelfIdView::new(data)?.into())),
+        MessageType::System => Ok(RemoteIDMessage::System(SystemView::new(data)?.into())),
+        MessageType::MessagePack => parse_message_pack(data),
+
+        MessageType::Invalid => unreachable!("rejected by message_type"),
+    }
+}
 
-    // Geodetic Altitude
-    let geodetic_altitude = u16::from_le_bytes(get_bytes!(buffer, 15, 2)) as f32 / 2.0 - 1000.;
+fn parse_message_pack(buffer: &[u8]) -> Result<RemoteIDMessage, DecodeError> {
+    // Single Message Size
+    let message_size = get_bytes!(buffer, 1, 1) as usize;
+    if message_size != message_pack::MESSAGE_BYTE_SIZE {
+        return Err(DecodeError::InvalidMessagePack);
+    }
 
-    // Geodetic Altitude
-    let height = u16::from_le_bytes(get_bytes!(buffer, 17, 2)) as f32 / 2.0 - 1000.;
+    // Number of Messages
+    let message_count = get_bytes!(buffer, 2, 1) as usize;
+    if message_count > message_pack::MAX_MESSAGES {
+        return Err(DecodeError::InvalidMessagePack);
+    }
+    ensure_len(buffer, message_pack::byte_size(message_count))?;
+
+    // Messages
+    let messages = buffer[message_pack::HEADER_BYTE_SIZE..message_pack::byte_size(message_count)]
+        .chunks(message_size)
+        .map(|message| match from_message_buffer(message)? {
+            // message packs must not be nested
+            RemoteIDMessage::MessagePack(_) => Err(DecodeError::InvalidMessagePack),
+            message => Ok(message),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(RemoteIDMessage::MessagePack(messages))
+}
 
-    // Vertical / Horizontal Accuracy
-    let accuracy = get_bytes!(buffer, 19, 1);
-    let vertical_accuracy = VerticalAccuracy::from(get_bits!(accuracy, 7..4));
-    let horizontal_accuracy = HorizontalAccuracy::from(get_bits!(accuracy, 3..0));
+fn parse_authentication(buffer: &[u8]) -> Result<RemoteIDMessage, DecodeError> {
+    // Authentication Type: Bits [7..4]
+    let auth_type = AuthType::from(get_bits!(buffer[1], 7..4));
+    // Page Number: Bits [3..0]
+    let page_number = get_bits!(buffer[1], 3..0);
+
+    let page = if page_number == 0 {
+        // Last Page Index
+        let last_page_index = get_bytes!(buffer, 2, 1);
+
+        // Length
+        //   Total bytes of authentication data over all pages
+        let length = get_bytes!(buffer, 3, 1);
+        if !authentication::is_valid_length(last_page_index, length) {
+            return Err(DecodeError::InvalidAuthenticationLength);
+        }
 
-    let accuracy = get_bytes!(buffer, 20, 1);
-    let baro_altitude_accuracy = VerticalAccuracy::from(get_bits!(accuracy, 7..4));
-    let speed_accuracy = SpeedAccuracy::from(get_bits!(accuracy, 3..0));
+        // Timestamp
+        let unix_secs = u32::from_le_bytes(get_bytes!(buffer, 4, 4));
+        let timestamp = DateTime::from_timestamp(unix_secs as i64 + REMOTE_ID_EPOCH, 0)
+            .ok_or(DecodeError::InvalidTimestamp)?;
 
-    let timestamp = u16::from_le_bytes(get_bytes!(buffer, 21, 2)) as f32 / 10.;
+        // Authentication Data
+        let data = copy_to_array(get_bytes!(buffer, 8, FIRST_PAGE_DATA_BYTE_SIZE));
 
-    let timestamp_accuracy = get_bits!(get_bytes!(buffer, 23, 1), 3..0);
-    let timestamp_accuracy = if timestamp_accuracy == 0 {
-        None
+        AuthenticationPage::First {
+            last_page_index,
+            length,
+            timestamp,
+            data,
+        }
     } else {
-        Some(Duration::from_secs_f32(timestamp_accuracy as f32 * 0.1))
+        // Authentication Data
+        let data = copy_to_array(get_bytes!(buffer, 2, PAGE_DATA_BYTE_SIZE));
+
+        AuthenticationPage::Subsequent { page_number, data }
     };
 
-    Some(RemoteIDMessage::Location(Location {
-        height_type,
-        operational_status,
-        latidute,
-        longitude,
-        height,
-        pressure_altitude,
-        geodetic_altitude,
-        track_direction,
-        speed,
-        vertical_speed,
-        horizontal_accuracy,
-        vertical_accuracy,
-        baro_altitude_accuracy,
-        speed_accuracy,
-        timestamp,
-        timestamp_accuracy,
+    Ok(RemoteIDMessage::Authentication(Authentication {
+        auth_type,
+        page,
+        version: protocol_version(buffer)?,
     }))
 }
 
@@ -232,17 +221,29 @@ fn parse_location(buffer: &[u8]) -> Option<RemoteIDMessage> {
 mod test {
     extern crate std;
 
+    use basic_id::{BasicId, IdType, UAType};
+    use location::{HeightType, Location, OperationalStatus};
     use operator_id::{OperatorId, OperatorIdType};
+    use self_id::{DescriptionType, SelfId};
+    use system::{
+        ClassificationType, OperatorLocationType, System, UaCategory, UaClass, UaClassification,
+    };
+    use uas_id::UasId;
 
     use super::*;
+    use crate::codec::copy_to_id;
 
     #[test]
     fn decode_basic_id_1() {
         // DroneTag Mini
         let expected = RemoteIDMessage::BasicID(BasicId {
-            id_type: IdType::SerialNumber,
             ua_type: UAType::None,
-            uas_id: copy_to_id("10000000000000000009".as_bytes()),
+            uas_id: UasId::Invalid {
+                id_type: IdType::SerialNumber,
+                id: copy_to_id(b"10000000000000000009"),
+            },
+            reserved: [0; 3],
+            version: ProtocolVersion::default(),
         });
 
         let service_data = [
@@ -257,9 +258,13 @@ mod test {
     fn decode_basic_id_2() {
         // DroneTag BS
         let expected = RemoteIDMessage::BasicID(BasicId {
-            id_type: IdType::SerialNumber,
             ua_type: UAType::None,
-            uas_id: copy_to_id("10000000000000000009".as_bytes()),
+            uas_id: UasId::Invalid {
+                id_type: IdType::SerialNumber,
+                id: copy_to_id(b"10000000000000000009"),
+            },
+            reserved: [0; 3],
+            version: ProtocolVersion::default(),
         });
 
         let service_data = [
@@ -275,20 +280,23 @@ mod test {
         let expected = RemoteIDMessage::Location(Location {
             height_type: HeightType::AboveTakeoff,
             operational_status: OperationalStatus::Airborne,
-            speed: 0.,
-            pressure_altitude: 190.5,
-            geodetic_altitude: 210.0,
-            vertical_speed: 0.,
-            latidute: 49.874855,
-            longitude: 8.912173,
-            height: 0.,
-            track_direction: 337,
+            speed: Some(0.),
+            pressure_altitude: Some(190.5),
+            geodetic_altitude: Some(210.0),
+            vertical_speed: Some(0.),
+            latidute: Some(49.8748559),
+            longitude: Some(8.9121728),
+            height: Some(0.),
+            track_direction: Some(337),
             horizontal_accuracy: location::HorizontalAccuracy::LessThan_3_m,
             vertical_accuracy: location::VerticalAccuracy::LessThan_3_m,
             baro_altitude_accuracy: location::VerticalAccuracy::Unknown,
             speed_accuracy: location::SpeedAccuracy::LessThan_third_mps,
-            timestamp: 361.0,
+            timestamp: Some(361.0),
             timestamp_accuracy: None,
+            reserved: [0; 3],
+            speed_multiplier: false,
+            version: ProtocolVersion::default(),
         });
 
         let service_data = [
@@ -303,20 +311,23 @@ mod test {
         let expected = RemoteIDMessage::Location(Location {
             height_type: HeightType::AboveTakeoff,
             operational_status: OperationalStatus::Airborne,
-            speed: 5.25,
-            pressure_altitude: 201.5,
-            geodetic_altitude: 218.0,
-            vertical_speed: 0.,
-            latidute: 49.875015,
-            longitude: 8.912442,
-            height: 11.0,
-            track_direction: 52,
+            speed: Some(5.25),
+            pressure_altitude: Some(201.5),
+            geodetic_altitude: Some(218.0),
+            vertical_speed: Some(0.),
+            latidute: Some(49.875014),
+            longitude: Some(8.9124421),
+            height: Some(11.0),
+            track_direction: Some(52),
             horizontal_accuracy: location::HorizontalAccuracy::LessThan_3_m,
             vertical_accuracy: location::VerticalAccuracy::LessThan_3_m,
             baro_altitude_accuracy: location::VerticalAccuracy::Unknown,
             speed_accuracy: location::SpeedAccuracy::LessThan_third_mps,
-            timestamp: 886.0,
+            timestamp: Some(886.0),
             timestamp_accuracy: None,
+            reserved: [0; 3],
+            speed_multiplier: false,
+            version: ProtocolVersion::default(),
         });
 
         let service_data = [
@@ -340,6 +351,8 @@ mod test {
         let expected = RemoteIDMessage::OperatorId(OperatorId {
             id_type: OperatorIdType::OperatorId,
             operator_id: copy_to_id("NULL".as_bytes()),
+            reserved: [0; 3],
+            version: ProtocolVersion::default(),
         });
 
         let service_data = [
@@ -353,6 +366,8 @@ mod test {
         let expected = RemoteIDMessage::OperatorId(OperatorId {
             id_type: OperatorIdType::OperatorId,
             operator_id: copy_to_id("FIN87astrdge12k8".as_bytes()),
+            reserved: [0; 3],
+            version: ProtocolVersion::default(),
         });
 
         let service_data = [
@@ -362,25 +377,199 @@ mod test {
         assert_eq!(expected, from_service_data(&service_data).unwrap());
     }
 
+    #[test]
+    fn decode_authentication_first_page() {
+        let expected = RemoteIDMessage::Authentication(Authentication {
+            auth_type: AuthType::UasIdSignature,
+            page: AuthenticationPage::First {
+                last_page_index: 2,
+                length: 63,
+                timestamp: DateTime::parse_from_rfc3339("2024-07-04T14:05:54Z")
+                    .unwrap()
+                    .to_utc(),
+                data: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17],
+            },
+            version: ProtocolVersion::default(),
+        });
+
+        let service_data = [
+            13, 4, 34, 16, 2, 63, 194, 254, 91, 10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14,
+            15, 16, 17,
+        ];
+        assert_eq!(expected, from_service_data(&service_data).unwrap());
+    }
+
+    #[test]
+    fn reject_invalid_authentication_length() {
+        let mut service_data = [
+            13, 4, 34, 16, 2, 63, 194, 254, 91, 10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14,
+            15, 16, 17,
+        ];
+        // 64 bytes need 3 pages
+        service_data[5] = 64;
+        assert_eq!(
+            Err(DecodeError::InvalidAuthenticationLength),
+            from_service_data(&service_data)
+        );
+
+        // at most 16 pages
+        service_data[4] = 16;
+        service_data[5] = 255;
+        assert_eq!(
+            Err(DecodeError::InvalidAuthenticationLength),
+            from_service_data(&service_data)
+        );
+    }
+
+    #[test]
+    fn decode_authentication_subsequent_page() {
+        let expected = RemoteIDMessage::Authentication(Authentication {
+            auth_type: AuthType::PrivateUse(10),
+            page: AuthenticationPage::Subsequent {
+                page_number: 15,
+                data: [0xAA; PAGE_DATA_BYTE_SIZE],
+            },
+            version: ProtocolVersion::default(),
+        });
+
+        let mut service_data = [0xAA; 27];
+        service_data[0..4].copy_from_slice(&[13, 5, 34, 0xAF]);
+        assert_eq!(expected, from_service_data(&service_data).unwrap());
+    }
+
+    #[test]
+    fn decode_message_pack() {
+        let expected = RemoteIDMessage::MessagePack(alloc::vec![
+            RemoteIDMessage::BasicID(BasicId {
+                ua_type: UAType::None,
+                uas_id: UasId::Invalid {
+                    id_type: IdType::SerialNumber,
+                    id: copy_to_id(b"10000000000000000009"),
+                },
+                reserved: [0; 3],
+                version: ProtocolVersion::default(),
+            }),
+            RemoteIDMessage::OperatorId(OperatorId {
+                id_type: OperatorIdType::OperatorId,
+                operator_id: copy_to_id("NULL".as_bytes()),
+                reserved: [0; 3],
+                version: ProtocolVersion::default(),
+            }),
+        ]);
+
+        let service_data = [
+            13, 9, 242, 25, 2, 2, 16, 49, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48,
+            48, 48, 48, 48, 57, 0, 0, 0, 82, 0, 78, 85, 76, 76, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(expected, from_service_data(&service_data).unwrap());
+    }
+
+    #[test]
+    fn decode_message_pack_truncated() {
+        let service_data = [13, 9, 242, 25, 2, 2, 16, 49, 48];
+        assert_eq!(
+            Err(DecodeError::BufferTooShort {
+                expected: 53,
+                actual: 7
+            }),
+            from_service_data(&service_data)
+        );
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(
+            Err(DecodeError::BufferTooShort {
+                expected: 2,
+                actual: 1
+            }),
+            from_service_data(&[13])
+        );
+        assert_eq!(
+            Err(DecodeError::InvalidAdCode(12)),
+            from_service_data(&[12, 0, 2])
+        );
+        assert_eq!(
+            Err(DecodeError::BufferTooShort {
+                expected: 25,
+                actual: 3
+            }),
+            from_service_data(&[13, 0, 2, 16, 49])
+        );
+        assert_eq!(
+            Err(DecodeError::UnsupportedMessageType(6)),
+            from_message_buffer(&[0x62; 25])
+        );
+        assert_eq!(
+            Err(DecodeError::InvalidProtocolVersion(3)),
+            from_message_buffer(&[0x03; 25])
+        );
+
+        let mut location = [0u8; 25];
+        location[0] = 0x12;
+        location[21..23].copy_from_slice(&36001u16.to_le_bytes());
+        assert_eq!(
+            Err(DecodeError::InvalidTimestamp),
+            from_message_buffer(&location)
+        );
+    }
+
+    #[test]
+    fn decode_never_panics() {
+        let mut buffer = [0u8; 60];
+        for len in 0..buffer.len() {
+            for first_byte in 0..=u8::MAX {
+                for fill in [0x00, 0x19, 0x55, 0xFF] {
+                    buffer.fill(fill);
+                    buffer[0] = first_byte;
+                    buffer[2] = first_byte;
+                    let _ = from_message_buffer(&buffer[..len]);
+                    let _ = from_service_data(&buffer[..len]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn decode_self_id() {
+        let expected = RemoteIDMessage::SelfId(SelfId {
+            description_type: DescriptionType::Emergency,
+            description: copy_to_array("Lost link".as_bytes()),
+            version: ProtocolVersion::default(),
+        });
+
+        let service_data = [
+            13, 7, 50, 1, 76, 111, 115, 116, 32, 108, 105, 110, 107, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0,
+        ];
+        assert_eq!(expected, from_service_data(&service_data).unwrap());
+    }
+
     #[test]
     fn decode_system_1() {
         let expected = RemoteIDMessage::System(System {
             classification_type: ClassificationType::EuropeanUnion,
             operator_location_type: OperatorLocationType::TakeOff,
-            operator_latidute: 49.874855,
-            operator_longitude: 8.912173,
-            operator_altitude: 210.,
-            area_ceiling: -1000.,
+            operator_latidute: Some(49.8748547),
+            operator_longitude: Some(8.9121724),
+            operator_altitude: Some(210.),
+            area_ceiling: None,
             area_count: 1,
-            area_floor: -1000.,
+            area_floor: None,
             area_radius: 250.,
             ua_classification: UaClassification {
-                category: UaCategory::Specific,
+                category: UaCategory::Open,
                 class: UaClass::Undefined,
             },
-            timestamp: DateTime::parse_from_rfc3339(&"2024-07-04T14:05:54Z")
-                .unwrap()
-                .to_utc(),
+            timestamp: Some(
+                DateTime::parse_from_rfc3339("2024-07-04T14:05:54Z")
+                    .unwrap()
+                    .to_utc(),
+            ),
+            reserved: [0; 2],
+            legacy_reserved: [0; 7],
+            version: ProtocolVersion::default(),
         });
 
         let service_data = [
@@ -389,4 +578,62 @@ mod test {
         ];
         assert_eq!(expected, from_service_data(&service_data).unwrap());
     }
+
+    #[test]
+    fn decode_system_f3411_19() {
+        // same frame as above, but with protocol version 0
+        let expected = RemoteIDMessage::System(System {
+            classification_type: ClassificationType::Undeclared,
+            operator_location_type: OperatorLocationType::TakeOff,
+            operator_latidute: Some(49.8748547),
+            operator_longitude: Some(8.9121724),
+            operator_altitude: None,
+            area_ceiling: None,
+            area_count: 1,
+            area_floor: None,
+            area_radius: 250.,
+            ua_classification: UaClassification::undefined(),
+            timestamp: None,
+            // the classification type and everything after the area floor is reserved
+            reserved: [0x04, 0],
+            legacy_reserved: [16, 116, 9, 194, 254, 91, 10],
+            version: ProtocolVersion::F3411_19,
+        });
+
+        let service_data = [
+            13, 3, 64, 4, 131, 76, 186, 29, 188, 227, 79, 5, 1, 0, 25, 0, 0, 0, 0, 16, 116, 9, 194,
+            254, 91, 10, 0,
+        ];
+        assert_eq!(
+            Ok(ProtocolVersion::F3411_19),
+            protocol_version(&service_data[2..])
+        );
+        assert_eq!(expected, from_service_data(&service_data).unwrap());
+    }
+
+    #[test]
+    fn decode_system_f3411_20() {
+        let service_data = [
+            13, 3, 65, 4, 131, 76, 186, 29, 188, 227, 79, 5, 1, 0, 25, 0, 0, 0, 0, 16, 116, 9, 194,
+            254, 91, 10, 0,
+        ];
+        assert_eq!(
+            Ok(ProtocolVersion::F3411_20),
+            protocol_version(&service_data[2..])
+        );
+
+        let RemoteIDMessage::System(system) = from_service_data(&service_data).unwrap() else {
+            panic!("expected system message");
+        };
+        assert_eq!(Some(210.), system.operator_altitude);
+        assert!(system.timestamp.is_some());
+    }
+
+    #[test]
+    fn default_system_roundtrip() {
+        let system = RemoteIDMessage::System(System::builder().build().unwrap());
+        let service_data = crate::codec::encode::to_service_data(&system, 0);
+        assert_eq!([0; 4], service_data[2 + 20..2 + 24]);
+        assert_eq!(system, from_service_data(&service_data).unwrap());
+    }
 }
@@ -1,18 +1,99 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use authentication::{
+    Authentication, AuthenticationData, AuthenticationPage, FIRST_PAGE_DATA_BYTE_SIZE,
+    MAX_AUTH_DATA_BYTE_SIZE, MAX_PAGES, PAGE_DATA_BYTE_SIZE,
+};
 use basic_id::BasicId;
+use chrono::{DateTime, Utc};
+use core::ops::RangeInclusive;
 use location::HeightType;
 use location::Location;
 use location::OperationalStatus;
 
 extern crate std;
 
+use crate::bitmask;
+use crate::codec::{
+    copy_to_array, encode_altitude, encode_coordinate, round, MessageType, ProtocolVersion,
+    UNKNOWN_SPEED, UNKNOWN_TIMESTAMP, UNKNOWN_TRACK_DIRECTION, UNKNOWN_VERTICAL_SPEED,
+};
 use crate::data::operator_id::OperatorId;
-use crate::data::system::ClassificationType;
+use crate::data::self_id::SelfId;
+use crate::data::self_id::MAX_DESCRIPTION_BYTE_SIZE;
 use crate::data::system::System;
+use crate::data::uas_id::{UasId, UasIdError};
 use crate::data::*;
 use crate::MAX_ID_BYTE_SIZE;
 use crate::OPEN_DRONE_ID_AD_CODE;
+use crate::REMOTE_ID_EPOCH;
 
-const VERSION: u8 = 2;
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EncodeError {
+    /// The value of the named field can not be represented on the wire
+    OutOfRange { field: &'static str },
+    /// The UAS ID does not match its ID type
+    InvalidUasId(UasIdError),
+    /// The target buffer can not hold the encoded message
+    BufferTooSmall { expected: usize, actual: usize },
+}
+
+impl core::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            EncodeError::OutOfRange { field } => write!(f, "{field} is out of range"),
+            EncodeError::InvalidUasId(error) => write!(f, "invalid UAS ID: {error}"),
+            EncodeError::BufferTooSmall { expected, actual } => {
+                write!(
+                    f,
+                    "buffer too small: expected {expected} bytes, got {actual}"
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for EncodeError {}
+
+/// Like [to_service_data], but fails instead of truncating out of range values
+pub fn try_to_service_data(
+    msg: &RemoteIDMessage,
+    message_counter: u8,
+) -> Result<[u8; 27], EncodeError> {
+    let mut data = [0u8; 27];
+
+    data[0] = OPEN_DRONE_ID_AD_CODE;
+    data[1] = message_counter;
+
+    try_to_message_buffer(msg, &mut data[2..])?;
+
+    Ok(data)
+}
+
+/// Like [to_message_buffer], but fails instead of truncating out of range values
+/// or panicking on a short buffer
+pub fn try_to_message_buffer(msg: &RemoteIDMessage, data: &mut [u8]) -> Result<(), EncodeError> {
+    try_encode_message(msg, None, data)
+}
+
+fn try_encode_message(
+    msg: &RemoteIDMessage,
+    forced_version: Option<ProtocolVersion>,
+    data: &mut [u8],
+) -> Result<(), EncodeError> {
+    validate(msg)?;
+
+    let expected = message_buffer_size(msg);
+    if data.len() < expected {
+        return Err(EncodeError::BufferTooSmall {
+            expected,
+            actual: data.len(),
+        });
+    }
+
+    encode_message(msg, forced_version, data);
+    Ok(())
+}
 
 pub fn to_service_data(msg: &RemoteIDMessage, message_counter: u8) -> [u8; 27] {
     let mut data = [0u8; 27];
@@ -25,85 +106,512 @@ pub fn to_service_data(msg: &RemoteIDMessage, message_counter: u8) -> [u8; 27] {
     data
 }
 
+/// Service data of variable length, as used with Bluetooth 5 extended advertising
+///
+/// Unlike [to_service_data] this can carry message packs.
+pub fn to_extended_service_data(msg: &RemoteIDMessage, message_counter: u8) -> Vec<u8> {
+    let mut data = vec![0u8; message_buffer_size(msg) + 2];
+
+    data[0] = OPEN_DRONE_ID_AD_CODE;
+    data[1] = message_counter;
+
+    to_message_buffer(msg, &mut data[2..]);
+
+    data
+}
+
+/// Like [to_extended_service_data], but fails instead of truncating out of range values
+pub fn try_to_extended_service_data(
+    msg: &RemoteIDMessage,
+    message_counter: u8,
+) -> Result<Vec<u8>, EncodeError> {
+    let mut data = vec![0u8; message_buffer_size(msg) + 2];
+
+    data[0] = OPEN_DRONE_ID_AD_CODE;
+    data[1] = message_counter;
+
+    try_to_message_buffer(msg, &mut data[2..])?;
+
+    Ok(data)
+}
+
+/// Number of bytes required to encode the message
+pub fn message_buffer_size(msg: &RemoteIDMessage) -> usize {
+    match msg {
+        RemoteIDMessage::MessagePack(messages) => message_pack::byte_size(messages.len()),
+        _ => message_pack::MESSAGE_BYTE_SIZE,
+    }
+}
+
+/// Encode the message with its own protocol version
 pub fn to_message_buffer(msg: &RemoteIDMessage, data: &mut [u8]) {
+    encode_message(msg, None, data)
+}
+
+/// Like [to_service_data], but emulates a transmitter of an older protocol version
+pub fn to_service_data_with_version(
+    msg: &RemoteIDMessage,
+    message_counter: u8,
+    version: ProtocolVersion,
+) -> [u8; 27] {
+    let mut data = [0u8; 27];
+
+    data[0] = OPEN_DRONE_ID_AD_CODE;
+    data[1] = message_counter;
+
+    to_message_buffer_with_version(msg, version, &mut data[2..]);
+
+    data
+}
+
+/// Like [to_message_buffer], but emulates a transmitter of an older protocol version
+///
+/// The version of the message, and of all messages of a message pack, is ignored.
+pub fn to_message_buffer_with_version(
+    msg: &RemoteIDMessage,
+    version: ProtocolVersion,
+    data: &mut [u8],
+) {
+    encode_message(msg, Some(version), data)
+}
+
+/// Like [to_service_data_with_version], but fails instead of truncating out of range values
+pub fn try_to_service_data_with_version(
+    msg: &RemoteIDMessage,
+    message_counter: u8,
+    version: ProtocolVersion,
+) -> Result<[u8; 27], EncodeError> {
+    let mut data = [0u8; 27];
+
+    data[0] = OPEN_DRONE_ID_AD_CODE;
+    data[1] = message_counter;
+
+    try_to_message_buffer_with_version(msg, version, &mut data[2..])?;
+
+    Ok(data)
+}
+
+/// Like [try_to_extended_service_data], but emulates a transmitter of an older protocol version
+pub fn try_to_extended_service_data_with_version(
+    msg: &RemoteIDMessage,
+    message_counter: u8,
+    version: ProtocolVersion,
+) -> Result<Vec<u8>, EncodeError> {
+    let mut data = vec![0u8; message_buffer_size(msg) + 2];
+
+    data[0] = OPEN_DRONE_ID_AD_CODE;
+    data[1] = message_counter;
+
+    try_to_message_buffer_with_version(msg, version, &mut data[2..])?;
+
+    Ok(data)
+}
+
+/// Like [to_message_buffer_with_version], but fails instead of truncating out of range values
+/// or panicking on a short buffer
+pub fn try_to_message_buffer_with_version(
+    msg: &RemoteIDMessage,
+    version: ProtocolVersion,
+    data: &mut [u8],
+) -> Result<(), EncodeError> {
+    try_encode_message(msg, Some(version), data)
+}
+
+fn encode_message(msg: &RemoteIDMessage, forced_version: Option<ProtocolVersion>, data: &mut [u8]) {
+    let version = forced_version.unwrap_or_else(|| msg.version());
+    let version_nibble: u8 = version.into();
     match msg {
         RemoteIDMessage::BasicID(basic_id) => {
-            data[0] = (basic_id::MESSAGE_TYPE << 4) | VERSION;
+            data[0] = (basic_id::MESSAGE_TYPE << 4) | version_nibble;
             encode_basic_id(basic_id, data);
         }
 
         RemoteIDMessage::Location(location) => {
-            data[0] = (location::MESSAGE_TYPE << 4) | VERSION;
+            data[0] = (location::MESSAGE_TYPE << 4) | version_nibble;
             encode_location(location, data);
         }
 
+        RemoteIDMessage::Authentication(authentication) => {
+            data[0] = (authentication::MESSAGE_TYPE << 4) | version_nibble;
+            encode_authentication(authentication, data);
+        }
+
         RemoteIDMessage::OperatorId(operator_id) => {
-            data[0] = (operator_id::MESSAGE_TYPE << 4) | VERSION;
+            data[0] = (operator_id::MESSAGE_TYPE << 4) | version_nibble;
             encode_operator_id(operator_id, data);
         }
 
+        RemoteIDMessage::SelfId(self_id) => {
+            data[0] = (self_id::MESSAGE_TYPE << 4) | version_nibble;
+            encode_self_id(self_id, data);
+        }
+
         RemoteIDMessage::System(system) => {
-            data[0] = (system::MESSAGE_TYPE << 4) | VERSION;
-            encode_system(system, data);
+            data[0] = (system::MESSAGE_TYPE << 4) | version_nibble;
+            encode_system(system, version, data);
         }
+
+        RemoteIDMessage::MessagePack(messages) => {
+            data[0] = (message_pack::MESSAGE_TYPE << 4) | version_nibble;
+            encode_message_pack(messages, forced_version, data);
+        }
+    }
+}
+
+/// Split authentication data into numbered pages
+///
+/// Fails if the data does not fit into the authentication pages.
+pub fn to_authentication_pages(
+    auth: &AuthenticationData,
+) -> Result<Vec<Authentication>, EncodeError> {
+    let max_len = usize::min(
+        MAX_AUTH_DATA_BYTE_SIZE,
+        FIRST_PAGE_DATA_BYTE_SIZE + (MAX_PAGES - 1) * PAGE_DATA_BYTE_SIZE,
+    );
+    check_range(auth.data.len(), 0..=max_len, "data")?;
+
+    let (first, rest) = auth
+        .data
+        .split_at(usize::min(auth.data.len(), FIRST_PAGE_DATA_BYTE_SIZE));
+    let subsequent = rest.chunks(PAGE_DATA_BYTE_SIZE);
+
+    let mut pages = Vec::with_capacity(subsequent.len() + 1);
+    pages.push(Authentication {
+        auth_type: auth.auth_type,
+        page: AuthenticationPage::First {
+            last_page_index: subsequent.len() as u8,
+            length: auth.data.len() as u8,
+            timestamp: auth.timestamp,
+            data: copy_to_array(first),
+        },
+        version: ProtocolVersion::default(),
+    });
+    for (i, chunk) in subsequent.enumerate() {
+        pages.push(Authentication {
+            auth_type: auth.auth_type,
+            page: AuthenticationPage::Subsequent {
+                page_number: i as u8 + 1,
+                data: copy_to_array(chunk),
+            },
+            version: ProtocolVersion::default(),
+        });
+    }
+
+    Ok(pages)
+}
+
+/// Keeps a separate message counter for every message type, as required of transmitters
+///
+/// Every encoded message counts as a new message. To repeat a message, e.g. on several
+/// advertising channels, advertise the returned payload again instead of encoding it again.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceDataEncoder {
+    counters: [u8; 16],
+    version: Option<ProtocolVersion>,
+}
+
+impl ServiceDataEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encoder emulating a transmitter of an older protocol version, see
+    /// [to_message_buffer_with_version]
+    pub fn with_version(version: ProtocolVersion) -> Self {
+        Self {
+            version: Some(version),
+            ..Self::default()
+        }
+    }
+
+    /// Counter the next message of the type will be sent with
+    pub fn counter(&self, message_type: MessageType) -> u8 {
+        self.counters[message_type as usize & 0xF]
+    }
+
+    /// Service data of a single message for Bluetooth legacy advertising
+    pub fn encode(&mut self, msg: &RemoteIDMessage) -> Result<[u8; 27], EncodeError> {
+        let counter = self.counter(MessageType::from(msg));
+        let data = match self.version {
+            Some(version) => try_to_service_data_with_version(msg, counter, version)?,
+            None => try_to_service_data(msg, counter)?,
+        };
+        self.increment(msg);
+        Ok(data)
+    }
+
+    /// Service data of variable length, also for message packs, e.g. for Bluetooth 5
+    /// extended advertising
+    pub fn encode_extended(&mut self, msg: &RemoteIDMessage) -> Result<Vec<u8>, EncodeError> {
+        let counter = self.counter(MessageType::from(msg));
+        let data = match self.version {
+            Some(version) => try_to_extended_service_data_with_version(msg, counter, version)?,
+            None => try_to_extended_service_data(msg, counter)?,
+        };
+        self.increment(msg);
+        Ok(data)
+    }
+
+    /// Service data of a message pack made of the messages
+    pub fn encode_message_pack(
+        &mut self,
+        messages: &[RemoteIDMessage],
+    ) -> Result<Vec<u8>, EncodeError> {
+        self.encode_extended(&RemoteIDMessage::MessagePack(messages.to_vec()))
+    }
+
+    fn increment(&mut self, msg: &RemoteIDMessage) {
+        let counter = &mut self.counters[MessageType::from(msg) as usize & 0xF];
+        *counter = counter.wrapping_add(1);
+    }
+}
+
+fn check_range<T: PartialOrd>(
+    value: T,
+    range: RangeInclusive<T>,
+    field: &'static str,
+) -> Result<(), EncodeError> {
+    if !range.contains(&value) {
+        return Err(EncodeError::OutOfRange { field });
+    }
+    Ok(())
+}
+
+/// Make sure every field of the message can be represented on the wire
+fn validate(msg: &RemoteIDMessage) -> Result<(), EncodeError> {
+    match msg {
+        RemoteIDMessage::BasicID(basic_id) => validate_basic_id(basic_id),
+        RemoteIDMessage::Location(location) => validate_location(location),
+        RemoteIDMessage::Authentication(authentication) => validate_authentication(authentication),
+        RemoteIDMessage::SelfId(_) => Ok(()),
+        RemoteIDMessage::System(system) => validate_system(system),
+        RemoteIDMessage::OperatorId(_) => Ok(()),
+        RemoteIDMessage::MessagePack(messages) => {
+            check_range(messages.len(), 0..=message_pack::MAX_MESSAGES, "messages")?;
+            for message in messages {
+                if let RemoteIDMessage::MessagePack(_) = message {
+                    return Err(EncodeError::OutOfRange { field: "messages" });
+                }
+                validate(message)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+// Altitudes are encoded as (altitude + 1000 m) / 0.5 in a u16, 0 is reserved for unknown
+const ALTITUDE_RANGE: RangeInclusive<f32> = -999.5..=(u16::MAX as f32 * 0.5 - 1000.);
+
+fn check_optional_range<T: PartialOrd>(
+    value: Option<T>,
+    range: RangeInclusive<T>,
+    field: &'static str,
+) -> Result<(), EncodeError> {
+    value.map_or(Ok(()), |value| check_range(value, range, field))
+}
+
+pub(crate) fn validate_basic_id(msg: &BasicId) -> Result<(), EncodeError> {
+    check_range(u8::from(msg.uas_id.id_type()), 0..=0xF, "id_type")?;
+    check_range(u8::from(msg.ua_type), 0..=0xF, "ua_type")?;
+    if let UasId::Invalid { id_type, id } = msg.uas_id {
+        UasId::parse(id_type, &id).map_err(EncodeError::InvalidUasId)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_location(msg: &Location) -> Result<(), EncodeError> {
+    check_range(
+        u8::from(msg.operational_status),
+        0..=0xF,
+        "operational_status",
+    )?;
+    check_range(
+        u8::from(msg.vertical_accuracy),
+        0..=0xF,
+        "vertical_accuracy",
+    )?;
+    check_range(
+        u8::from(msg.horizontal_accuracy),
+        0..=0xF,
+        "horizontal_accuracy",
+    )?;
+    check_range(
+        u8::from(msg.baro_altitude_accuracy),
+        0..=0xF,
+        "baro_altitude_accuracy",
+    )?;
+    check_range(u8::from(msg.speed_accuracy), 0..=0xF, "speed_accuracy")?;
+    check_optional_range(msg.track_direction, 0..=359, "track_direction")?;
+    check_optional_range(msg.speed, 0.0..=254.25, "speed")?;
+    check_optional_range(msg.vertical_speed, -62.0..=62.0, "vertical_speed")?;
+    check_optional_range(msg.latidute, -90.0..=90.0, "latitude")?;
+    check_optional_range(msg.longitude, -180.0..=180.0, "longitude")?;
+    check_optional_range(msg.pressure_altitude, ALTITUDE_RANGE, "pressure_altitude")?;
+    check_optional_range(msg.geodetic_altitude, ALTITUDE_RANGE, "geodetic_altitude")?;
+    check_optional_range(msg.height, ALTITUDE_RANGE, "height")?;
+    check_optional_range(msg.timestamp, 0.0..=3600.0, "timestamp")?;
+    if let Some(acc) = msg.timestamp_accuracy {
+        check_range(acc.as_secs_f32(), 0.0..=1.5, "timestamp_accuracy")?;
+    }
+    Ok(())
+}
+
+fn validate_authentication(msg: &Authentication) -> Result<(), EncodeError> {
+    check_range(u8::from(msg.auth_type), 0..=0xF, "auth_type")?;
+    check_range(msg.page_number(), 0..=0xF, "page_number")?;
+    if let AuthenticationPage::First {
+        last_page_index,
+        length,
+        timestamp,
+        ..
+    } = msg.page
+    {
+        check_range(last_page_index, 0..=0xF, "last_page_index")?;
+        if !authentication::is_valid_length(last_page_index, length) {
+            return Err(EncodeError::OutOfRange { field: "length" });
+        }
+        check_timestamp(&timestamp, "timestamp")?;
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_system(msg: &System) -> Result<(), EncodeError> {
+    check_range(
+        u8::from(msg.classification_type),
+        0..=0x7,
+        "classification_type",
+    )?;
+    check_range(
+        u8::from(msg.operator_location_type),
+        0..=0x3,
+        "operator_location_type",
+    )?;
+    check_range(
+        u8::from(msg.ua_classification.category),
+        0..=0xF,
+        "ua_category",
+    )?;
+    check_range(u8::from(msg.ua_classification.class), 0..=0xF, "ua_class")?;
+    check_optional_range(msg.operator_latidute, -90.0..=90.0, "operator_latitude")?;
+    check_optional_range(msg.operator_longitude, -180.0..=180.0, "operator_longitude")?;
+    check_range(msg.area_radius, 0.0..=2550.0, "area_radius")?;
+    check_optional_range(msg.area_ceiling, ALTITUDE_RANGE, "area_ceiling")?;
+    check_optional_range(msg.area_floor, ALTITUDE_RANGE, "area_floor")?;
+    check_optional_range(msg.operator_altitude, ALTITUDE_RANGE, "operator_altitude")?;
+    if let Some(timestamp) = msg.timestamp {
+        check_timestamp(&timestamp, "timestamp")?;
+        // the epoch itself is encoded as 0, which is reserved for unknown
+        if timestamp.timestamp() == REMOTE_ID_EPOCH {
+            return Err(EncodeError::OutOfRange { field: "timestamp" });
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn check_timestamp(
+    timestamp: &DateTime<Utc>,
+    field: &'static str,
+) -> Result<(), EncodeError> {
+    check_range(
+        timestamp.timestamp(),
+        REMOTE_ID_EPOCH..=(REMOTE_ID_EPOCH + u32::MAX as i64),
+        field,
+    )
+}
+
+fn encode_message_pack(
+    messages: &[RemoteIDMessage],
+    forced_version: Option<ProtocolVersion>,
+    target: &mut [u8],
+) {
+    // Single Message Size
+    target[1] = message_pack::MESSAGE_BYTE_SIZE as u8;
+
+    // Number of Messages
+    let messages = &messages[..usize::min(messages.len(), message_pack::MAX_MESSAGES)];
+    target[2] = messages.len() as u8;
+
+    // Messages
+    let chunks = target[message_pack::HEADER_BYTE_SIZE..message_pack::byte_size(messages.len())]
+        .chunks_mut(message_pack::MESSAGE_BYTE_SIZE);
+    for (message, chunk) in messages.iter().zip(chunks) {
+        encode_message(message, forced_version, chunk);
     }
 }
 
 fn encode_basic_id(msg: &BasicId, target: &mut [u8]) {
-    let first_nibble: u8 = msg.id_type.into();
+    // ID Type: Bits [7..4]
+    // UA Type: Bits [3..0]
+    let first_nibble: u8 = msg.uas_id.id_type().into();
     let last_nibble: u8 = msg.ua_type.into();
     target[1] = (first_nibble << 4) | last_nibble;
 
-    target[2..(MAX_ID_BYTE_SIZE + 2)].clone_from_slice(&msg.uas_id);
+    // UAS ID
+    target[2..(MAX_ID_BYTE_SIZE + 2)].clone_from_slice(&msg.uas_id.to_bytes());
+
+    // Reserved
+    target[(MAX_ID_BYTE_SIZE + 2)..(MAX_ID_BYTE_SIZE + 5)].clone_from_slice(&msg.reserved);
 }
 
 fn encode_location(msg: &Location, target: &mut [u8]) {
     let operational_status: u8 = OperationalStatus::into(msg.operational_status);
     let height_type: u8 = HeightType::into(msg.height_type);
-    let ew_direction_segment: u8 = if msg.track_direction > 180 { 1 } else { 0 };
 
-    let speed_multiplier: u8 = if msg.speed > 255. * 0.25 { 1 } else { 0 };
+    let track_direction = msg.track_direction.unwrap_or(UNKNOWN_TRACK_DIRECTION);
+    let ew_direction_segment: u8 = if track_direction >= 180 { 1 } else { 0 };
+
+    // 63.75 m/s can be encoded with and without the speed multiplier
+    let speed_multiplier: u8 = match msg.speed {
+        Some(speed) if speed < 255. * 0.25 => 0,
+        Some(speed) if speed == 255. * 0.25 => msg.speed_multiplier.into(),
+        _ => 1,
+    };
 
-    target[1] =
-        operational_status << 3 | height_type << 2 | ew_direction_segment << 1 | speed_multiplier;
+    target[1] = operational_status << 4
+        | (msg.reserved[0] & bitmask!(3, 3))
+        | height_type << 2
+        | ew_direction_segment << 1
+        | speed_multiplier;
 
     // Track Direction
-    target[2] = if msg.track_direction > 180 {
-        (msg.track_direction - 180) as u8
+    target[2] = if track_direction >= 180 {
+        (track_direction - 180) as u8
     } else {
-        msg.track_direction as u8
+        track_direction as u8
     };
 
     // Speed
-    target[3] = if msg.speed <= 255. * 0.25 {
-        (msg.speed / 0.25) as u8
-    } else if msg.speed > 255. * 0.25 && msg.speed < 254.25 {
-        ((msg.speed - (255. * 0.25)) / 0.75) as u8
-    } else {
-        254
+    target[3] = match msg.speed {
+        Some(speed) if speed_multiplier == 0 => (speed / 0.25) as u8,
+        Some(speed) if speed < 254.25 => ((speed - (255. * 0.25)) / 0.75) as u8,
+        Some(_) => 254,
+        None => UNKNOWN_SPEED,
     };
 
     // Vertical Speed
-    target[4] = (msg.vertical_speed / 0.5) as u8;
+    let vertical_speed = msg
+        .vertical_speed
+        .map_or(UNKNOWN_VERTICAL_SPEED, |vs| (vs / 0.5) as i8);
+    target[4] = vertical_speed as u8;
 
     // Latitude
-    let lat = (msg.latidute * f32::powf(10., 7.)) as u32;
+    let lat = encode_coordinate(msg.latidute);
     target[5..9].clone_from_slice(&lat.to_le_bytes());
 
     // Longitude
-    let lon = (msg.longitude * f32::powf(10., 7.)) as u32;
+    let lon = encode_coordinate(msg.longitude);
     target[9..13].clone_from_slice(&lon.to_le_bytes());
 
     // Pressure Altitude
-    let pressure_altitude = ((msg.pressure_altitude + 1000.) / 0.5) as u16;
+    let pressure_altitude = encode_altitude(msg.pressure_altitude);
     target[13..15].clone_from_slice(&pressure_altitude.to_le_bytes());
 
     // Geodetic Altitude
-    let geodetic_altitude = ((msg.geodetic_altitude + 1000.) / 0.5) as u16;
+    let geodetic_altitude = encode_altitude(msg.geodetic_altitude);
     target[15..17].clone_from_slice(&geodetic_altitude.to_le_bytes());
 
     // Height
-    let height = ((msg.height + 1000.) / 0.5) as u16;
+    let height = encode_altitude(msg.height);
     target[17..19].clone_from_slice(&height.to_le_bytes());
 
     // Vertical / Horizontal Accuracy
@@ -117,19 +625,54 @@ fn encode_location(msg: &Location, target: &mut [u8]) {
     target[20] = baro_altitude_accuracy << 4 | speed_accuracy;
 
     // Timestamp
-    let timestamp = (msg.timestamp * 10.) as u16;
+    let timestamp = msg.timestamp.map_or(UNKNOWN_TIMESTAMP, |timestamp| {
+        round(timestamp as f64 * 10.) as u16
+    });
     target[21..23].clone_from_slice(&timestamp.to_le_bytes());
 
     // Reserved / Timestamp Accuracy
     let timestamp_accuracy = if let Some(acc) = msg.timestamp_accuracy {
-        (acc.as_secs_f32() / 0.1) as u8
+        (acc.as_millis() / 100) as u8
     } else {
         0
     };
-    target[23] = timestamp_accuracy;
+    target[23] = (msg.reserved[1] & bitmask!(4, 7)) | timestamp_accuracy;
 
     // Reserved
-    target[24] = 0;
+    target[24] = msg.reserved[2];
+}
+
+fn encode_authentication(msg: &Authentication, target: &mut [u8]) {
+    // Authentication Type: Bits [7..4]
+    // Page Number: Bits [3..0]
+    let auth_type: u8 = msg.auth_type.into();
+    target[1] = (auth_type << 4) | (msg.page_number() & 0x0F);
+
+    match msg.page {
+        AuthenticationPage::First {
+            last_page_index,
+            length,
+            timestamp,
+            data,
+        } => {
+            // Last Page Index
+            target[2] = last_page_index;
+
+            // Length
+            target[3] = length;
+
+            // Timestamp
+            let unix_timestamp = (timestamp.timestamp() - REMOTE_ID_EPOCH) as u32;
+            target[4..8].clone_from_slice(&unix_timestamp.to_le_bytes());
+
+            // Authentication Data
+            target[8..(FIRST_PAGE_DATA_BYTE_SIZE + 8)].clone_from_slice(&data);
+        }
+        AuthenticationPage::Subsequent { data, .. } => {
+            // Authentication Data
+            target[2..(PAGE_DATA_BYTE_SIZE + 2)].clone_from_slice(&data);
+        }
+    }
 }
 
 fn encode_operator_id(msg: &OperatorId, target: &mut [u8]) {
@@ -138,21 +681,43 @@ fn encode_operator_id(msg: &OperatorId, target: &mut [u8]) {
 
     // Operator ID
     target[2..(MAX_ID_BYTE_SIZE + 2)].clone_from_slice(&msg.operator_id);
+
+    // Reserved
+    target[(MAX_ID_BYTE_SIZE + 2)..(MAX_ID_BYTE_SIZE + 5)].clone_from_slice(&msg.reserved);
 }
 
-fn encode_system(msg: &System, target: &mut [u8]) {
-    // Classification Type: Bits [4..2]
+fn encode_self_id(msg: &SelfId, target: &mut [u8]) {
+    // Description Type
+    target[1] = Into::<u8>::into(msg.description_type);
+
+    // Description
+    target[2..(MAX_DESCRIPTION_BYTE_SIZE + 2)].clone_from_slice(&msg.description);
+}
+
+fn encode_system(msg: &System, version: ProtocolVersion, target: &mut [u8]) {
+    // F3411-19 reserves everything after the area floor
+    let legacy = version == ProtocolVersion::F3411_19;
+
+    // Reserved: Bits [7..5]
+    // Classification Type: Bits [4..2], reserved in F3411-19
     // Operator Location/Altitude source type: Bits [1..0]
-    let classification_type: u8 = msg.classification_type as u8;
-    let operator_location_type: u8 = msg.operator_location_type as u8;
-    target[1] = (classification_type << 2) | operator_location_type;
+    let (reserved, classification_type): (u8, u8) = if legacy {
+        (msg.reserved[0] & bitmask!(2, 7), 0)
+    } else {
+        (
+            msg.reserved[0] & bitmask!(5, 7),
+            msg.classification_type.into(),
+        )
+    };
+    let operator_location_type: u8 = msg.operator_location_type.into();
+    target[1] = reserved | (classification_type << 2) | operator_location_type;
 
     // Operator Latitude
-    let lat = (msg.operator_latidute * f32::powf(10., 7.)) as u32;
+    let lat = encode_coordinate(msg.operator_latidute);
     target[2..6].clone_from_slice(&lat.to_le_bytes());
 
     // Operator Longitude
-    let lon = (msg.operator_longitude * f32::powf(10., 7.)) as u32;
+    let lon = encode_coordinate(msg.operator_longitude);
     target[6..10].clone_from_slice(&lon.to_le_bytes());
 
     // Area Count
@@ -163,61 +728,75 @@ fn encode_system(msg: &System, target: &mut [u8]) {
     target[12] = area_radius;
 
     // Area Ceiling
-    let area_ceiling = ((msg.area_ceiling + 1000.) / 0.5) as u16;
+    let area_ceiling = encode_altitude(msg.area_ceiling);
     target[13..15].clone_from_slice(&area_ceiling.to_le_bytes());
 
     // Area Floor
-    let area_floor = ((msg.area_floor + 1000.) / 0.5) as u16;
+    let area_floor = encode_altitude(msg.area_floor);
     target[15..17].clone_from_slice(&area_floor.to_le_bytes());
 
-    // UA Classification
-    target[17] = if msg.classification_type == ClassificationType::EuropeanUnion {
-        let (cat, class): (u8, u8) = (
-            msg.ua_classification.category.into(),
-            msg.ua_classification.class.into(),
-        );
+    if legacy {
+        // Reserved
+        target[17..24].clone_from_slice(&msg.legacy_reserved);
+        target[24] = msg.reserved[1];
+        return;
+    }
 
-        cat << 3 | class
-    } else {
-        0
-    };
+    // UA Classification
+    //   Category: Bits [7..4], Class: Bits [3..0]
+    let (cat, class): (u8, u8) = (
+        msg.ua_classification.category.into(),
+        msg.ua_classification.class.into(),
+    );
+    target[17] = cat << 4 | class;
 
     // Operator Altitude
-    let operator_altitude = ((msg.operator_altitude + 1000.) / 0.5) as u16;
+    let operator_altitude = encode_altitude(msg.operator_altitude);
     target[18..20].clone_from_slice(&operator_altitude.to_le_bytes());
 
     // Timestamp
-    let unix_timestamp = (msg.timestamp.timestamp() - 1546300800) as u32;
+    let unix_timestamp = msg.timestamp.map_or(0, |timestamp| {
+        (timestamp.timestamp() - REMOTE_ID_EPOCH) as u32
+    });
     target[20..24].clone_from_slice(&unix_timestamp.to_le_bytes());
 
     // Reserved
-    target[24] = 0;
+    target[24] = msg.reserved[1];
 }
 
 #[cfg(test)]
 mod test {
     extern crate std;
 
+    use alloc::vec;
     use chrono::DateTime;
 
+    use super::authentication::{AuthType, AuthenticationData, AuthenticationPage};
     use super::basic_id::{IdType, UAType};
     use super::location::{HeightType, Location, OperationalStatus};
-    use crate::codec::copy_to_id;
-    use crate::codec::encode::to_service_data;
+    use crate::codec::encode::{
+        to_authentication_pages, to_extended_service_data, to_service_data, try_to_message_buffer,
+        try_to_service_data, EncodeError, ServiceDataEncoder,
+    };
+    use crate::codec::encode::{to_service_data_with_version, try_to_service_data_with_version};
+    use crate::codec::{copy_to_array, copy_to_id, MessageType, ProtocolVersion};
     use crate::data::basic_id::BasicId;
     use crate::data::operator_id::{OperatorId, OperatorIdType};
+    use crate::data::self_id::{DescriptionType, SelfId};
     use crate::data::system::{
         ClassificationType, OperatorLocationType, System, UaCategory, UaClass, UaClassification,
     };
+    use crate::data::uas_id::{SerialNumber, UasId, UasIdError};
     use crate::data::RemoteIDMessage;
 
     #[test]
     fn encode_basic_id() {
         // DroneTag Mini
         let basic_id = RemoteIDMessage::BasicID(BasicId {
-            id_type: IdType::SerialNumber,
             ua_type: UAType::None,
-            uas_id: copy_to_id("1596F359746167260749".as_bytes()),
+            uas_id: UasId::SerialNumber(SerialNumber::parse("1596F359746167260749").unwrap()),
+            reserved: [0; 3],
+            version: ProtocolVersion::default(),
         });
 
         let service_data = [
@@ -232,9 +811,10 @@ mod test {
     fn encode_basic_id_2() {
         // DroneTag BS
         let basic_id = RemoteIDMessage::BasicID(BasicId {
-            id_type: IdType::SerialNumber,
             ua_type: UAType::None,
-            uas_id: copy_to_id("1596F3170CE908F55122".as_bytes()),
+            uas_id: UasId::SerialNumber(SerialNumber::parse("1596F3170CE908F55122").unwrap()),
+            reserved: [0; 3],
+            version: ProtocolVersion::default(),
         });
 
         let expected = [
@@ -249,21 +829,24 @@ mod test {
     fn encode_location_1() {
         let location = RemoteIDMessage::Location(Location {
             height_type: HeightType::AboveTakeoff,
-            operational_status: OperationalStatus::RemoteIdSystemFailure,
-            speed: 10.,
-            vertical_speed: 10.,
-            pressure_altitude: 190.5,
-            geodetic_altitude: 210.0,
+            operational_status: OperationalStatus::Airborne,
+            speed: Some(10.),
+            vertical_speed: Some(10.),
+            pressure_altitude: Some(190.5),
+            geodetic_altitude: Some(210.0),
             baro_altitude_accuracy: crate::data::location::VerticalAccuracy::Unknown,
             horizontal_accuracy: crate::data::location::HorizontalAccuracy::LessThan_3_m,
             speed_accuracy: crate::data::location::SpeedAccuracy::LessThan_third_mps,
             vertical_accuracy: crate::data::location::VerticalAccuracy::LessThan_3_m,
-            track_direction: 77,
-            latidute: 49.874855,
-            longitude: 8.912173,
-            height: 0.,
-            timestamp: 361.0,
+            track_direction: Some(77),
+            latidute: Some(49.8748544),
+            longitude: Some(8.9121736),
+            height: Some(0.),
+            timestamp: Some(361.0),
             timestamp_accuracy: None,
+            reserved: [0; 3],
+            speed_multiplier: false,
+            version: ProtocolVersion::default(),
         });
         let expected = [
             13, 1, 18, 32, 77, 40, 20, 128, 76, 186, 29, 200, 227, 79, 5, 77, 9, 116, 9, 208, 7,
@@ -272,25 +855,60 @@ mod test {
         assert_eq!(expected, to_service_data(&location, 1));
     }
 
+    #[test]
+    fn encode_location_unknown() {
+        let location = RemoteIDMessage::Location(Location {
+            height_type: HeightType::AboveTakeoff,
+            operational_status: OperationalStatus::Undeclared,
+            speed: None,
+            vertical_speed: None,
+            pressure_altitude: None,
+            geodetic_altitude: None,
+            baro_altitude_accuracy: crate::data::location::VerticalAccuracy::Unknown,
+            horizontal_accuracy: crate::data::location::HorizontalAccuracy::Unknown,
+            speed_accuracy: crate::data::location::SpeedAccuracy::Unknown,
+            vertical_accuracy: crate::data::location::VerticalAccuracy::Unknown,
+            track_direction: None,
+            latidute: None,
+            longitude: None,
+            height: None,
+            timestamp: None,
+            timestamp_accuracy: None,
+            reserved: [0; 3],
+            speed_multiplier: false,
+            version: ProtocolVersion::default(),
+        });
+        let expected = [
+            13, 1, 18, 3, 181, 255, 126, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255,
+            0, 0,
+        ];
+        assert_eq!(expected, to_service_data(&location, 1));
+    }
+
     #[test]
     fn encode_system() {
         let system = RemoteIDMessage::System(System {
             classification_type: ClassificationType::EuropeanUnion,
             operator_location_type: OperatorLocationType::TakeOff,
-            operator_latidute: 49.874855,
-            operator_longitude: 8.912173,
-            operator_altitude: 210.,
-            area_ceiling: -1000.,
+            operator_latidute: Some(49.8748544),
+            operator_longitude: Some(8.9121736),
+            operator_altitude: Some(210.),
+            area_ceiling: None,
             area_count: 1,
-            area_floor: -1000.,
+            area_floor: None,
             area_radius: 250.,
             ua_classification: UaClassification {
-                category: UaCategory::Specific,
+                category: UaCategory::Open,
                 class: UaClass::Undefined,
             },
-            timestamp: DateTime::parse_from_rfc3339(&"2024-07-04T14:05:54Z")
-                .unwrap()
-                .to_utc(),
+            timestamp: Some(
+                DateTime::parse_from_rfc3339("2024-07-04T14:05:54Z")
+                    .unwrap()
+                    .to_utc(),
+            ),
+            reserved: [0; 2],
+            legacy_reserved: [0; 7],
+            version: ProtocolVersion::default(),
         });
 
         let service_data = [
@@ -305,6 +923,8 @@ mod test {
         let operator_id = RemoteIDMessage::OperatorId(OperatorId {
             id_type: OperatorIdType::OperatorId,
             operator_id: copy_to_id("NULL".as_bytes()),
+            reserved: [0; 3],
+            version: ProtocolVersion::default(),
         });
 
         let service_data = [
@@ -318,6 +938,8 @@ mod test {
         let operator_id = RemoteIDMessage::OperatorId(OperatorId {
             id_type: OperatorIdType::OperatorId,
             operator_id: copy_to_id("FIN87astrdge12k8".as_bytes()),
+            reserved: [0; 3],
+            version: ProtocolVersion::default(),
         });
 
         let service_data = [
@@ -326,4 +948,397 @@ mod test {
         ];
         assert_eq!(service_data, to_service_data(&operator_id, 3));
     }
+
+    #[test]
+    fn encode_self_id() {
+        let self_id = RemoteIDMessage::SelfId(SelfId {
+            description_type: DescriptionType::Text,
+            description: copy_to_array("DroneTag".as_bytes()),
+            version: ProtocolVersion::default(),
+        });
+
+        let service_data = [
+            13, 2, 50, 0, 68, 114, 111, 110, 101, 84, 97, 103, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0,
+        ];
+        assert_eq!(service_data, to_service_data(&self_id, 2));
+    }
+
+    #[test]
+    fn encode_authentication_pages() {
+        let timestamp = DateTime::parse_from_rfc3339("2024-07-04T14:05:54Z")
+            .unwrap()
+            .to_utc();
+        let auth_data = AuthenticationData {
+            auth_type: AuthType::UasIdSignature,
+            timestamp,
+            data: (1..=63).collect(),
+        };
+
+        let pages = to_authentication_pages(&auth_data).unwrap();
+        assert_eq!(3, pages.len());
+        assert_eq!(
+            AuthenticationPage::First {
+                last_page_index: 2,
+                length: 63,
+                timestamp,
+                data: [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17],
+            },
+            pages[0].page
+        );
+
+        let service_data = [
+            13, 4, 34, 16, 2, 63, 194, 254, 91, 10, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14,
+            15, 16, 17,
+        ];
+        assert_eq!(
+            service_data,
+            to_service_data(&RemoteIDMessage::Authentication(pages[0]), 4)
+        );
+
+        let service_data = [
+            13, 5, 34, 18, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58,
+            59, 60, 61, 62, 63,
+        ];
+        assert_eq!(
+            service_data,
+            to_service_data(&RemoteIDMessage::Authentication(pages[2]), 5)
+        );
+    }
+
+    #[test]
+    fn encode_authentication_pages_too_long() {
+        let auth_data = AuthenticationData {
+            auth_type: AuthType::MessageSetSignature,
+            timestamp: DateTime::parse_from_rfc3339("2024-07-04T14:05:54Z")
+                .unwrap()
+                .to_utc(),
+            data: [0; 256].to_vec(),
+        };
+
+        assert_eq!(
+            Err(EncodeError::OutOfRange { field: "data" }),
+            to_authentication_pages(&auth_data)
+        );
+    }
+
+    #[test]
+    fn encode_message_pack() {
+        let message_pack = RemoteIDMessage::MessagePack(vec![
+            RemoteIDMessage::BasicID(BasicId {
+                ua_type: UAType::None,
+                uas_id: UasId::SerialNumber(SerialNumber::parse("1596F359746167260749").unwrap()),
+                reserved: [0; 3],
+                version: ProtocolVersion::default(),
+            }),
+            RemoteIDMessage::OperatorId(OperatorId {
+                id_type: OperatorIdType::OperatorId,
+                operator_id: copy_to_id("NULL".as_bytes()),
+                reserved: [0; 3],
+                version: ProtocolVersion::default(),
+            }),
+        ]);
+
+        let service_data = [
+            13, 7, 242, 25, 2, 2, 16, 49, 53, 57, 54, 70, 51, 53, 57, 55, 52, 54, 49, 54, 55, 50,
+            54, 48, 55, 52, 57, 0, 0, 0, 82, 0, 78, 85, 76, 76, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(
+            service_data.to_vec(),
+            to_extended_service_data(&message_pack, 7)
+        );
+    }
+
+    fn location() -> Location {
+        Location {
+            height_type: HeightType::AboveTakeoff,
+            operational_status: OperationalStatus::Airborne,
+            speed: Some(10.),
+            vertical_speed: Some(10.),
+            pressure_altitude: Some(190.5),
+            geodetic_altitude: Some(210.0),
+            baro_altitude_accuracy: crate::data::location::VerticalAccuracy::Unknown,
+            horizontal_accuracy: crate::data::location::HorizontalAccuracy::LessThan_3_m,
+            speed_accuracy: crate::data::location::SpeedAccuracy::LessThan_third_mps,
+            vertical_accuracy: crate::data::location::VerticalAccuracy::LessThan_3_m,
+            track_direction: Some(77),
+            latidute: Some(49.8748544),
+            longitude: Some(8.9121736),
+            height: Some(0.),
+            timestamp: Some(361.0),
+            timestamp_accuracy: None,
+            reserved: [0; 3],
+            speed_multiplier: false,
+            version: ProtocolVersion::default(),
+        }
+    }
+
+    #[test]
+    fn try_encode_location() {
+        let location = RemoteIDMessage::Location(location());
+        assert_eq!(
+            Ok(to_service_data(&location, 1)),
+            try_to_service_data(&location, 1)
+        );
+    }
+
+    #[test]
+    fn try_encode_lowest_altitude() {
+        let location = RemoteIDMessage::Location(Location {
+            height: Some(-999.5),
+            ..location()
+        });
+        let service_data = try_to_service_data(&location, 1).unwrap();
+        assert_eq!([1, 0], service_data[2 + 17..2 + 19]);
+        assert_eq!(
+            Ok(location),
+            crate::codec::decode::from_service_data(&service_data)
+        );
+    }
+
+    #[test]
+    fn try_encode_location_out_of_range() {
+        let cases = [
+            (
+                Location {
+                    latidute: Some(90.5),
+                    ..location()
+                },
+                "latitude",
+            ),
+            (
+                Location {
+                    longitude: Some(f64::NAN),
+                    ..location()
+                },
+                "longitude",
+            ),
+            (
+                Location {
+                    speed: Some(254.5),
+                    ..location()
+                },
+                "speed",
+            ),
+            (
+                Location {
+                    vertical_speed: Some(-62.5),
+                    ..location()
+                },
+                "vertical_speed",
+            ),
+            (
+                Location {
+                    geodetic_altitude: Some(-1000.5),
+                    ..location()
+                },
+                "geodetic_altitude",
+            ),
+            (
+                Location {
+                    height: Some(-1000.0),
+                    ..location()
+                },
+                "height",
+            ),
+            (
+                Location {
+                    track_direction: Some(360),
+                    ..location()
+                },
+                "track_direction",
+            ),
+        ];
+
+        for (location, field) in cases {
+            assert_eq!(
+                Err(EncodeError::OutOfRange { field }),
+                try_to_service_data(&RemoteIDMessage::Location(location), 1)
+            );
+        }
+    }
+
+    #[test]
+    fn try_encode_malformed_serial_number() {
+        let basic_id = RemoteIDMessage::BasicID(BasicId {
+            ua_type: UAType::None,
+            uas_id: UasId::Invalid {
+                id_type: IdType::SerialNumber,
+                id: copy_to_id(b"1596F35974"),
+            },
+            reserved: [0; 3],
+            version: ProtocolVersion::default(),
+        });
+
+        assert_eq!(
+            Err(EncodeError::InvalidUasId(UasIdError::LengthMismatch)),
+            try_to_service_data(&basic_id, 1)
+        );
+    }
+
+    #[test]
+    fn try_encode_system_timestamp_before_2019() {
+        let system = RemoteIDMessage::System(System {
+            classification_type: ClassificationType::EuropeanUnion,
+            operator_location_type: OperatorLocationType::TakeOff,
+            operator_latidute: Some(49.8748544),
+            operator_longitude: Some(8.9121736),
+            operator_altitude: Some(210.),
+            area_ceiling: None,
+            area_count: 1,
+            area_floor: None,
+            area_radius: 250.,
+            ua_classification: UaClassification::undefined(),
+            timestamp: Some(
+                DateTime::parse_from_rfc3339("2018-12-31T23:59:59Z")
+                    .unwrap()
+                    .to_utc(),
+            ),
+            reserved: [0; 2],
+            legacy_reserved: [0; 7],
+            version: ProtocolVersion::default(),
+        });
+
+        assert_eq!(
+            Err(EncodeError::OutOfRange { field: "timestamp" }),
+            try_to_service_data(&system, 1)
+        );
+
+        // encoded as 0, which is unknown
+        let epoch = System::builder()
+            .timestamp(DateTime::from_timestamp(crate::REMOTE_ID_EPOCH, 0).unwrap())
+            .build();
+        assert_eq!(Err(EncodeError::OutOfRange { field: "timestamp" }), epoch);
+    }
+
+    #[test]
+    fn try_encode_buffer_too_small() {
+        let location = RemoteIDMessage::Location(location());
+        assert_eq!(
+            Err(EncodeError::BufferTooSmall {
+                expected: 25,
+                actual: 24
+            }),
+            try_to_message_buffer(&location, &mut [0; 24])
+        );
+
+        let message_pack = RemoteIDMessage::MessagePack(vec![location.clone(), location]);
+        assert_eq!(
+            Err(EncodeError::BufferTooSmall {
+                expected: 53,
+                actual: 25
+            }),
+            try_to_service_data(&message_pack, 1)
+        );
+    }
+
+    #[test]
+    fn encode_system_f3411_19() {
+        let system = RemoteIDMessage::System(System {
+            classification_type: ClassificationType::EuropeanUnion,
+            operator_location_type: OperatorLocationType::TakeOff,
+            operator_latidute: Some(49.8748544),
+            operator_longitude: Some(8.9121736),
+            operator_altitude: Some(210.),
+            area_ceiling: None,
+            area_count: 1,
+            area_floor: None,
+            area_radius: 250.,
+            ua_classification: UaClassification {
+                category: UaCategory::Specific,
+                class: UaClass::Undefined,
+            },
+            timestamp: Some(
+                DateTime::parse_from_rfc3339("2024-07-04T14:05:54Z")
+                    .unwrap()
+                    .to_utc(),
+            ),
+            reserved: [0; 2],
+            legacy_reserved: [0; 7],
+            version: ProtocolVersion::default(),
+        });
+
+        let service_data = [
+            13, 3, 64, 0, 128, 76, 186, 29, 200, 227, 79, 5, 1, 0, 25, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0,
+        ];
+        assert_eq!(
+            service_data,
+            to_service_data_with_version(&system, 3, ProtocolVersion::F3411_19)
+        );
+    }
+
+    #[test]
+    fn encoder_counts_per_message_type() {
+        let basic_id = RemoteIDMessage::BasicID(
+            BasicId::builder()
+                .serial_number("1596F359746167260749")
+                .build()
+                .unwrap(),
+        );
+        let location = RemoteIDMessage::Location(Location::builder().build().unwrap());
+        let mut encoder = ServiceDataEncoder::new();
+
+        assert_eq!(0, encoder.encode(&basic_id).unwrap()[1]);
+        assert_eq!(1, encoder.encode(&basic_id).unwrap()[1]);
+        assert_eq!(0, encoder.encode(&location).unwrap()[1]);
+        assert_eq!(2, encoder.counter(MessageType::BasicId));
+
+        let pack = encoder
+            .encode_message_pack(&[basic_id.clone(), location.clone()])
+            .unwrap();
+        assert_eq!(0, pack[1]);
+        assert_eq!(2 + 3 + 2 * 25, pack.len());
+        assert_eq!(1, encoder.counter(MessageType::MessagePack));
+        assert_eq!(2, encoder.counter(MessageType::BasicId));
+
+        // message packs do not fit into legacy advertising, the counter stays unchanged
+        assert!(encoder
+            .encode(&RemoteIDMessage::MessagePack(vec![basic_id.clone()]))
+            .is_err());
+        assert_eq!(1, encoder.counter(MessageType::MessagePack));
+    }
+
+    #[test]
+    fn try_encode_with_version() {
+        let mut location = Location::builder().speed(12.5).build().unwrap();
+        let msg = RemoteIDMessage::Location(location.clone());
+        let data = try_to_service_data_with_version(&msg, 1, ProtocolVersion::F3411_19).unwrap();
+        assert_eq!(0x10, data[2]);
+
+        location.speed = Some(300.);
+        let msg = RemoteIDMessage::Location(location);
+        assert_eq!(
+            Err(EncodeError::OutOfRange { field: "speed" }),
+            try_to_service_data_with_version(&msg, 1, ProtocolVersion::F3411_19)
+        );
+
+        let mut encoder = ServiceDataEncoder::with_version(ProtocolVersion::F3411_20);
+        assert_eq!(
+            Err(EncodeError::OutOfRange { field: "speed" }),
+            encoder.encode(&msg)
+        );
+        assert_eq!(0, encoder.counter(MessageType::Location));
+
+        let basic_id = RemoteIDMessage::BasicID(
+            BasicId::builder()
+                .serial_number("1596F359746167260749")
+                .build()
+                .unwrap(),
+        );
+        assert_eq!(0x01, encoder.encode(&basic_id).unwrap()[2]);
+        assert_eq!(0xF1, encoder.encode_message_pack(&[basic_id]).unwrap()[2]);
+    }
+
+    #[test]
+    fn encoder_counter_wraps() {
+        let location = RemoteIDMessage::Location(Location::builder().build().unwrap());
+        let mut encoder = ServiceDataEncoder::new();
+
+        for _ in 0..256 {
+            encoder.encode_extended(&location).unwrap();
+        }
+        assert_eq!(0, encoder.counter(MessageType::Location));
+    }
 }
@@ -1,11 +1,14 @@
+use crate::data::RemoteIDMessage;
 use crate::MAX_ID_BYTE_SIZE;
 
 pub mod decode;
 pub mod encode;
+pub mod view;
 
+/// Type of a message, the upper nibble of the header
 #[repr(u8)]
-#[derive(Debug, Copy, Clone)]
-enum MessageType {
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MessageType {
     BasicId = 0,
     Location = 1,
     Auth = 2,
@@ -33,31 +36,140 @@ impl From<u8> for MessageType {
     }
 }
 
-pub fn copy_to_id(slice: &[u8]) -> [u8; 20] {
-    let mut buffer = [0u8; MAX_ID_BYTE_SIZE];
-    let max = if slice.len() <= MAX_ID_BYTE_SIZE {
-        slice.len()
-    } else {
-        MAX_ID_BYTE_SIZE
-    };
+impl From<&RemoteIDMessage> for MessageType {
+    fn from(msg: &RemoteIDMessage) -> Self {
+        match msg {
+            RemoteIDMessage::BasicID(_) => MessageType::BasicId,
+            RemoteIDMessage::Location(_) => MessageType::Location,
+            RemoteIDMessage::Authentication(_) => MessageType::Auth,
+            RemoteIDMessage::SelfId(_) => MessageType::Selfid,
+            RemoteIDMessage::System(_) => MessageType::System,
+            RemoteIDMessage::OperatorId(_) => MessageType::OperatorId,
+            RemoteIDMessage::MessagePack(_) => MessageType::MessagePack,
+        }
+    }
+}
+
+/// A message as broadcast in service data, together with its message counter
+///
+/// Transmitters increment the counter of each message type whenever they send a new
+/// message of that type, so receivers can detect lost and repeated messages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceDataFrame {
+    pub counter: u8,
+    pub message: RemoteIDMessage,
+}
+
+impl ServiceDataFrame {
+    pub fn message_type(&self) -> MessageType {
+        MessageType::from(&self.message)
+    }
+}
+
+/// Version of the specification a message was encoded with, the lower nibble of the header
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProtocolVersion {
+    /// ASTM F3411-19, the System message carries no classification, altitude or timestamp
+    F3411_19,
+    /// ASTM F3411-20
+    F3411_20,
+    /// ASTM F3411-22a
+    #[default]
+    F3411_22a,
+    /// Reserved for private use, decoded like the latest version
+    PrivateUse,
+}
+
+impl TryFrom<u8> for ProtocolVersion {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ProtocolVersion::F3411_19),
+            1 => Ok(ProtocolVersion::F3411_20),
+            2 => Ok(ProtocolVersion::F3411_22a),
+            0xF => Ok(ProtocolVersion::PrivateUse),
+
+            _ => Err(value),
+        }
+    }
+}
+
+impl From<ProtocolVersion> for u8 {
+    fn from(val: ProtocolVersion) -> Self {
+        match val {
+            ProtocolVersion::F3411_19 => 0,
+            ProtocolVersion::F3411_20 => 1,
+            ProtocolVersion::F3411_22a => 2,
+            ProtocolVersion::PrivateUse => 0xF,
+        }
+    }
+}
+
+pub fn copy_to_id(slice: &[u8]) -> [u8; MAX_ID_BYTE_SIZE] {
+    copy_to_array(slice)
+}
+
+/// Copy the slice into a NUL padded array, truncating it if it is too long
+pub fn copy_to_array<const N: usize>(slice: &[u8]) -> [u8; N] {
+    let mut buffer = [0u8; N];
+    let max = if slice.len() <= N { slice.len() } else { N };
     buffer[0..max].copy_from_slice(&slice[0..max]);
     buffer
 }
 
+/// Track direction reserved for unknown directions
+const UNKNOWN_TRACK_DIRECTION: u16 = 361;
+
+/// Speed byte reserved for unknown speeds, 255 m/s with the speed multiplier set
+const UNKNOWN_SPEED: u8 = 255;
+
+/// Vertical speed reserved for unknown vertical speeds, 63 m/s
+const UNKNOWN_VERTICAL_SPEED: i8 = 126;
+
+/// Tenths of seconds reserved for unknown timestamps
+const UNKNOWN_TIMESTAMP: u16 = u16::MAX;
+
+/// Latitudes and longitudes are encoded as degrees * 10^7, 0 if unknown
+fn encode_coordinate(coordinate: Option<f64>) -> i32 {
+    coordinate.map_or(0, |c| round(c * 1e7) as i32)
+}
+
+fn decode_coordinate(value: i32) -> Option<f64> {
+    (value != 0).then(|| value as f64 / 1e7)
+}
+
+/// Round half away from zero, `f64::round` is not available without std
+fn round(value: f64) -> i64 {
+    if value < 0. {
+        (value - 0.5) as i64
+    } else {
+        (value + 0.5) as i64
+    }
+}
+
+/// Altitudes are encoded as (altitude + 1000 m) / 0.5, -1000 m if unknown
+fn encode_altitude(altitude: Option<f32>) -> u16 {
+    altitude.map_or(0, |a| ((a + 1000.) / 0.5) as u16)
+}
+
+fn decode_altitude(value: u16) -> Option<f32> {
+    (value != 0).then(|| value as f32 / 2. - 1000.)
+}
+
 #[macro_export]
 macro_rules! bitmask {
     ($lo:literal, $hi:literal) => {{
         let mut pattern = 1;
 
-        for _ in 0..($hi - $lo) {
+        let mut bit = $lo;
+        while bit < $hi {
             pattern = (pattern << 1) + 1;
+            bit += 1;
         }
 
-        for _ in 0..$lo {
-            pattern <<= 1;
-        }
-
-        pattern
+        pattern << $lo
     }};
 }
 
@@ -114,9 +226,15 @@ mod test {
     use chrono::{SubsecRound, Utc};
 
     use crate::{
-        codec::{copy_to_id, decode, encode},
+        codec::{copy_to_array, copy_to_id, decode, encode, ProtocolVersion},
         data::{
-            basic_id::BasicId, location::Location, operator_id::OperatorId, system::System,
+            authentication::{AuthType, AuthenticationData, AuthenticationReassembler},
+            basic_id::BasicId,
+            location::Location,
+            operator_id::OperatorId,
+            self_id::SelfId,
+            system::System,
+            uas_id::{SerialNumber, UasId},
             RemoteIDMessage,
         },
     };
@@ -208,12 +326,13 @@ mod test {
     #[test]
     fn test_recode_basic_id() {
         let basic_id = RemoteIDMessage::BasicID(BasicId {
-            id_type: crate::data::basic_id::IdType::None,
             ua_type: crate::data::basic_id::UAType::Aeroplane,
-            uas_id: copy_to_id("1\0".as_bytes()),
+            uas_id: UasId::None,
+            reserved: [0; 3],
+            version: ProtocolVersion::default(),
         });
 
-        let mut buf = [0u8; 22];
+        let mut buf = [0u8; 25];
         encode::to_message_buffer(&basic_id, &mut buf);
         let msg = decode::from_message_buffer(&buf).unwrap();
         std::dbg!(&msg);
@@ -225,20 +344,23 @@ mod test {
         let location = RemoteIDMessage::Location(Location {
             operational_status: crate::data::location::OperationalStatus::Undeclared,
             height_type: crate::data::location::HeightType::AboveGroundLevel,
-            speed: 4.0,
-            vertical_speed: 2.0,
-            pressure_altitude: 3.0,
-            geodetic_altitude: 4.0,
-            track_direction: 5,
+            speed: Some(4.0),
+            vertical_speed: Some(2.0),
+            pressure_altitude: Some(3.0),
+            geodetic_altitude: Some(4.0),
+            track_direction: Some(5),
             horizontal_accuracy: crate::data::location::HorizontalAccuracy::LessThan_10_NM,
             vertical_accuracy: crate::data::location::VerticalAccuracy::LessThan_10_m,
-            latidute: 6.0,
-            longitude: 7.0,
-            height: 8.0,
+            latidute: Some(6.0),
+            longitude: Some(7.0),
+            height: Some(8.0),
             baro_altitude_accuracy: crate::data::location::VerticalAccuracy::LessThan_10_m,
             speed_accuracy: crate::data::location::SpeedAccuracy::LessThan_10_mps,
-            timestamp: 9.0,
+            timestamp: Some(9.0),
             timestamp_accuracy: None,
+            reserved: [0; 3],
+            speed_multiplier: false,
+            version: ProtocolVersion::default(),
         });
 
         let mut buf = [0u8; 25];
@@ -249,11 +371,105 @@ mod test {
         assert_eq!(location, msg);
     }
 
+    fn location_at(latidute: f64, longitude: f64, vertical_speed: f32) -> RemoteIDMessage {
+        RemoteIDMessage::Location(Location {
+            operational_status: crate::data::location::OperationalStatus::Undeclared,
+            height_type: crate::data::location::HeightType::AboveTakeoff,
+            speed: Some(12.5),
+            vertical_speed: Some(vertical_speed),
+            pressure_altitude: Some(120.0),
+            geodetic_altitude: Some(150.0),
+            track_direction: Some(90),
+            horizontal_accuracy: crate::data::location::HorizontalAccuracy::LessThan_3_m,
+            vertical_accuracy: crate::data::location::VerticalAccuracy::LessThan_3_m,
+            latidute: Some(latidute),
+            longitude: Some(longitude),
+            height: Some(30.0),
+            baro_altitude_accuracy: crate::data::location::VerticalAccuracy::LessThan_3_m,
+            speed_accuracy: crate::data::location::SpeedAccuracy::LessThan_1_mps,
+            timestamp: Some(1234.5),
+            timestamp_accuracy: None,
+            reserved: [0; 3],
+            speed_multiplier: false,
+            version: ProtocolVersion::default(),
+        })
+    }
+
+    #[test]
+    fn test_recode_location_quadrants() {
+        let quadrants = [
+            // Frankfurt, north east
+            (50.125, 8.5),
+            // New York, north west
+            (40.75, -73.875),
+            // Sydney, south east
+            (-33.875, 151.25),
+            // Buenos Aires, south west
+            (-34.625, -58.375),
+        ];
+
+        for (latidute, longitude) in quadrants {
+            let location = location_at(latidute, longitude, 0.0);
+
+            let mut buf = [0u8; 25];
+            encode::to_message_buffer(&location, &mut buf);
+            let msg = decode::from_message_buffer(&buf).unwrap();
+
+            assert_eq!(location, msg);
+        }
+    }
+
+    #[test]
+    fn test_recode_location_unknown() {
+        let location = RemoteIDMessage::Location(Location {
+            operational_status: crate::data::location::OperationalStatus::Undeclared,
+            height_type: crate::data::location::HeightType::AboveTakeoff,
+            speed: None,
+            vertical_speed: None,
+            pressure_altitude: None,
+            geodetic_altitude: None,
+            track_direction: None,
+            horizontal_accuracy: crate::data::location::HorizontalAccuracy::Unknown,
+            vertical_accuracy: crate::data::location::VerticalAccuracy::Unknown,
+            latidute: None,
+            longitude: None,
+            height: None,
+            baro_altitude_accuracy: crate::data::location::VerticalAccuracy::Unknown,
+            speed_accuracy: crate::data::location::SpeedAccuracy::Unknown,
+            timestamp: None,
+            timestamp_accuracy: None,
+            reserved: [0; 3],
+            speed_multiplier: false,
+            version: ProtocolVersion::default(),
+        });
+
+        let mut buf = [0u8; 25];
+        encode::to_message_buffer(&location, &mut buf);
+        let msg = decode::from_message_buffer(&buf).unwrap();
+
+        assert_eq!(location, msg);
+    }
+
+    #[test]
+    fn test_recode_location_vertical_speed() {
+        for vertical_speed in [62.0, 3.5, 0.5, 0.0, -0.5, -3.5, -62.0] {
+            let location = location_at(50.125, 8.5, vertical_speed);
+
+            let mut buf = [0u8; 25];
+            encode::to_message_buffer(&location, &mut buf);
+            let msg = decode::from_message_buffer(&buf).unwrap();
+
+            assert_eq!(location, msg);
+        }
+    }
+
     #[test]
     fn test_recode_operator_id() {
         let location = RemoteIDMessage::OperatorId(OperatorId {
             id_type: crate::data::operator_id::OperatorIdType::Unknown(99),
             operator_id: copy_to_id("1\0".as_bytes()),
+            reserved: [0; 3],
+            version: ProtocolVersion::default(),
         });
 
         let mut buf = [0u8; 25];
@@ -263,23 +479,130 @@ mod test {
         assert_eq!(location, msg);
     }
 
+    #[test]
+    fn test_recode_authentication() {
+        let auth_data = AuthenticationData {
+            auth_type: AuthType::OperatorIdSignature,
+            timestamp: Utc::now().trunc_subsecs(0),
+            data: (0..=200).collect(),
+        };
+
+        let mut reassembler = AuthenticationReassembler::new();
+        let mut reassembled = None;
+        for page in encode::to_authentication_pages(&auth_data).unwrap() {
+            let message = RemoteIDMessage::Authentication(page);
+
+            let mut buf = [0u8; 25];
+            encode::to_message_buffer(&message, &mut buf);
+            let msg = decode::from_message_buffer(&buf).unwrap();
+            assert_eq!(message, msg);
+
+            if let RemoteIDMessage::Authentication(page) = msg {
+                reassembled = reassembler.push(page);
+            }
+        }
+
+        assert_eq!(Some(auth_data), reassembled);
+    }
+
+    #[test]
+    fn test_recode_self_id() {
+        let self_id = RemoteIDMessage::SelfId(SelfId {
+            description_type: crate::data::self_id::DescriptionType::PrivateUse(234),
+            description: copy_to_array("Survey flight\0".as_bytes()),
+            version: ProtocolVersion::default(),
+        });
+
+        let mut buf = [0u8; 25];
+        encode::to_message_buffer(&self_id, &mut buf);
+        let msg = decode::from_message_buffer(&buf).unwrap();
+
+        assert_eq!(self_id, msg);
+    }
+
+    #[test]
+    fn test_recode_message_pack() {
+        let message_pack = RemoteIDMessage::MessagePack(alloc::vec![
+            RemoteIDMessage::SelfId(SelfId {
+                description_type: crate::data::self_id::DescriptionType::Text,
+                description: copy_to_array("Survey flight".as_bytes()),
+                version: ProtocolVersion::default(),
+            }),
+            RemoteIDMessage::OperatorId(OperatorId {
+                id_type: crate::data::operator_id::OperatorIdType::OperatorId,
+                operator_id: copy_to_id("FIN87astrdge12k8".as_bytes()),
+                reserved: [0; 3],
+                version: ProtocolVersion::default(),
+            }),
+            RemoteIDMessage::BasicID(BasicId {
+                ua_type: crate::data::basic_id::UAType::HelicopterOrMultirotor,
+                uas_id: UasId::SerialNumber(SerialNumber::parse("1596F359746167260749").unwrap()),
+                reserved: [0; 3],
+                version: ProtocolVersion::default(),
+            }),
+        ]);
+
+        let mut buf = [0u8; 3 + 3 * 25];
+        assert_eq!(buf.len(), encode::message_buffer_size(&message_pack));
+        encode::to_message_buffer(&message_pack, &mut buf);
+        let msg = decode::from_message_buffer(&buf).unwrap();
+
+        assert_eq!(message_pack, msg);
+    }
+
+    #[test]
+    fn test_recode_system_quadrants() {
+        for (operator_latidute, operator_longitude) in [
+            (50.125, 8.5),
+            (40.75, -73.875),
+            (-33.875, 151.25),
+            (-34.625, -58.375),
+        ] {
+            let system = RemoteIDMessage::System(System {
+                classification_type: crate::data::system::ClassificationType::Undeclared,
+                operator_location_type: crate::data::system::OperatorLocationType::Dynamic,
+                operator_latidute: Some(operator_latidute),
+                operator_longitude: Some(operator_longitude),
+                area_count: 1,
+                area_radius: 0.0,
+                area_ceiling: None,
+                area_floor: None,
+                ua_classification: crate::data::system::UaClassification::undefined(),
+                operator_altitude: Some(123.0),
+                timestamp: Some(Utc::now().trunc_subsecs(0)),
+                reserved: [0; 2],
+                legacy_reserved: [0; 7],
+                version: ProtocolVersion::default(),
+            });
+
+            let mut buf = [0u8; 25];
+            encode::to_message_buffer(&system, &mut buf);
+            let msg = decode::from_message_buffer(&buf).unwrap();
+
+            assert_eq!(system, msg);
+        }
+    }
+
     #[test]
     fn test_recode_system() {
         let location = RemoteIDMessage::System(System {
             classification_type: crate::data::system::ClassificationType::EuropeanUnion,
             operator_location_type: crate::data::system::OperatorLocationType::Fixed,
-            operator_latidute: 10.0,
-            operator_longitude: 10.0,
+            operator_latidute: Some(10.0),
+            operator_longitude: Some(10.0),
             area_count: 11,
             area_radius: 1230.0,
-            area_ceiling: 1234.0,
-            area_floor: 1234.0,
+            area_ceiling: Some(1234.0),
+            area_floor: Some(1234.0),
             ua_classification: crate::data::system::UaClassification {
                 category: crate::data::system::UaCategory::Certified,
                 class: crate::data::system::UaClass::Class0,
             },
-            operator_altitude: 123.0,
-            timestamp: Utc::now().trunc_subsecs(0),
+            operator_altitude: Some(123.0),
+            timestamp: Some(Utc::now().trunc_subsecs(0)),
+            reserved: [0; 2],
+            legacy_reserved: [0; 7],
+            version: ProtocolVersion::default(),
         });
 
         let mut buf = [0u8; 25];
@@ -288,4 +611,87 @@ mod test {
 
         assert_eq!(location, msg);
     }
+
+    #[test]
+    fn test_recode_lossless() {
+        let frames: [[u8; 27]; 13] = [
+            // DroneTag Mini, Basic ID
+            [
+                13, 1, 2, 16, 49, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48,
+                48, 48, 57, 0, 0, 0,
+            ],
+            // DroneTag Mini, Location
+            [
+                13, 72, 18, 34, 157, 0, 0, 143, 76, 186, 29, 192, 227, 79, 5, 77, 9, 116, 9, 208,
+                7, 91, 4, 26, 14, 0, 0,
+            ],
+            [
+                13, 85, 18, 32, 52, 21, 0, 188, 82, 186, 29, 69, 238, 79, 5, 99, 9, 132, 9, 230, 7,
+                91, 4, 156, 34, 0, 0,
+            ],
+            // DroneTag Mini, Self ID
+            [
+                13, 7, 50, 1, 76, 111, 115, 116, 32, 108, 105, 110, 107, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0,
+            ],
+            // DroneTag Mini, System
+            [
+                13, 3, 66, 4, 131, 76, 186, 29, 188, 227, 79, 5, 1, 0, 25, 0, 0, 0, 0, 16, 116, 9,
+                194, 254, 91, 10, 0,
+            ],
+            // DroneTag Mini, Operator ID
+            [
+                13, 3, 82, 0, 70, 73, 78, 56, 55, 97, 115, 116, 114, 100, 103, 101, 49, 50, 107,
+                56, 0, 0, 0, 0, 0, 0, 0,
+            ],
+            // Basic ID with reserved ID type and reserved bytes
+            [
+                13, 0, 2, 127, 49, 50, 51, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2,
+                3,
+            ],
+            // Location with reserved status, accuracies and reserved bits
+            [
+                13, 0, 18, 95, 10, 100, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 223, 127, 16,
+                39, 163, 90,
+            ],
+            // System with reserved classification, location type, UA classification and reserved bits
+            [
+                13, 0, 66, 175, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 249, 0, 0, 0, 0, 0, 0,
+                119,
+            ],
+            // Basic ID, F3411-19
+            [
+                13, 1, 0, 16, 49, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48, 48,
+                48, 48, 57, 0, 0, 0,
+            ],
+            // Location, F3411-20
+            [
+                13, 72, 17, 34, 157, 0, 0, 143, 76, 186, 29, 192, 227, 79, 5, 77, 9, 116, 9, 208,
+                7, 91, 4, 26, 14, 0, 0,
+            ],
+            // Location, 63.75 m/s encoded with the speed multiplier
+            [
+                13, 85, 18, 33, 52, 0, 0, 188, 82, 186, 29, 69, 238, 79, 5, 99, 9, 132, 9, 230, 7,
+                91, 4, 156, 34, 0, 0,
+            ],
+            // System, F3411-19 with the reserved classification type and bytes in use
+            [
+                13, 3, 64, 4, 131, 76, 186, 29, 188, 227, 79, 5, 1, 0, 25, 0, 0, 0, 0, 16, 116, 9,
+                194, 254, 91, 10, 7,
+            ],
+        ];
+
+        for frame in frames {
+            let msg = decode::from_service_data(&frame).unwrap();
+            assert_eq!(frame, encode::to_service_data(&msg, frame[1]), "{msg:?}");
+        }
+
+        // Message Pack, F3411-20
+        let mut pack = alloc::vec![13, 9, 0xF1, 25, 2];
+        pack.extend_from_slice(&frames[10][2..]);
+        pack.extend_from_slice(&frames[9][2..]);
+        let msg = decode::from_service_data(&pack).unwrap();
+        assert_eq!(ProtocolVersion::F3411_20, msg.version());
+        assert_eq!(pack, encode::to_extended_service_data(&msg, 9));
+    }
 }
@@ -0,0 +1,531 @@
+//! Borrowed views over a single 25 byte message
+//!
+//! Views check the length and message type once and decode each field only when its
+//! accessor is called. IDs and descriptions are returned as references into the buffer.
+
+use core::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::data::basic_id::{BasicId, IdType, UAType};
+use crate::data::location::{
+    HeightType, HorizontalAccuracy, Location, OperationalStatus, SpeedAccuracy, VerticalAccuracy,
+};
+use crate::data::message_pack;
+use crate::data::operator_id::{OperatorId, OperatorIdType};
+use crate::data::self_id::{DescriptionType, SelfId, MAX_DESCRIPTION_BYTE_SIZE};
+use crate::data::system::{
+    ClassificationType, OperatorLocationType, System, UaCategory, UaClass, UaClassification,
+};
+use crate::data::uas_id::UasId;
+use crate::{bitmask, get_bits, get_bytes, MAX_ID_BYTE_SIZE, REMOTE_ID_EPOCH};
+
+use super::decode::{message_type, protocol_version, DecodeError};
+use super::{
+    decode_altitude, decode_coordinate, MessageType, ProtocolVersion, UNKNOWN_SPEED,
+    UNKNOWN_TIMESTAMP, UNKNOWN_VERTICAL_SPEED,
+};
+
+/// Check the buffer holds a complete message of the expected type, returns its version
+fn check_message(buffer: &[u8], expected: MessageType) -> Result<ProtocolVersion, DecodeError> {
+    let version = protocol_version(buffer)?;
+    let actual = message_type(buffer)?;
+    if actual != expected {
+        return Err(DecodeError::UnexpectedMessageType(actual as u8));
+    }
+    if buffer.len() < message_pack::MESSAGE_BYTE_SIZE {
+        return Err(DecodeError::BufferTooShort {
+            expected: message_pack::MESSAGE_BYTE_SIZE,
+            actual: buffer.len(),
+        });
+    }
+    Ok(version)
+}
+
+fn array_at<const N: usize>(buffer: &[u8], offset: usize) -> &[u8; N] {
+    buffer[offset..offset + N]
+        .try_into()
+        .expect("length checked on construction")
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct BasicIdView<'a> {
+    buffer: &'a [u8],
+    version: ProtocolVersion,
+}
+
+impl<'a> BasicIdView<'a> {
+    pub fn new(buffer: &'a [u8]) -> Result<Self, DecodeError> {
+        let version = check_message(buffer, MessageType::BasicId)?;
+        Ok(Self { buffer, version })
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    // ID Type: Bits [7..4]
+    pub fn id_type(&self) -> IdType {
+        IdType::from(get_bits!(self.buffer[1], 7..4))
+    }
+
+    // UA Type: Bits [3..0]
+    pub fn ua_type(&self) -> UAType {
+        UAType::from(get_bits!(self.buffer[1], 3..0))
+    }
+
+    /// UAS ID as transmitted, padded with NUL
+    pub fn uas_id_bytes(&self) -> &'a [u8; MAX_ID_BYTE_SIZE] {
+        array_at(self.buffer, 2)
+    }
+
+    /// UAS ID interpreted according to the ID type
+    pub fn uas_id(&self) -> UasId {
+        UasId::from_bytes(self.id_type(), self.uas_id_bytes())
+    }
+
+    pub fn reserved(&self) -> [u8; 3] {
+        get_bytes!(self.buffer, MAX_ID_BYTE_SIZE + 2, 3)
+    }
+}
+
+impl From<BasicIdView<'_>> for BasicId {
+    fn from(view: BasicIdView<'_>) -> Self {
+        BasicId {
+            ua_type: view.ua_type(),
+            uas_id: view.uas_id(),
+            version: view.version(),
+            reserved: view.reserved(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct LocationView<'a> {
+    buffer: &'a [u8],
+    version: ProtocolVersion,
+}
+
+impl<'a> LocationView<'a> {
+    pub fn new(buffer: &'a [u8]) -> Result<Self, DecodeError> {
+        let version = check_message(buffer, MessageType::Location)?;
+        Ok(Self { buffer, version })
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    fn status_flags(&self) -> u8 {
+        get_bytes!(self.buffer, 1, 1)
+    }
+
+    // Operational Status: Bits [7..4]
+    pub fn operational_status(&self) -> OperationalStatus {
+        OperationalStatus::from(get_bits!(self.status_flags(), 7..4))
+    }
+
+    // Height Type: Bit [2]
+    pub fn height_type(&self) -> HeightType {
+        HeightType::from(get_bits!(self.status_flags(), 2..2))
+    }
+
+    /// Degrees clockwise from true north
+    pub fn track_direction(&self) -> Option<u16> {
+        // E/W Direction Segment: Bit [1]
+        let ew_direction_segment = get_bits!(self.status_flags(), 1..1);
+
+        let track_direction = get_bytes!(self.buffer, 2, 1) as u16;
+        let track_direction = if ew_direction_segment > 0 {
+            track_direction + 180
+        } else {
+            track_direction
+        };
+        (track_direction < 360).then_some(track_direction)
+    }
+
+    // Speed Multiplier: Bit [0]
+    fn speed_multiplier_bit(&self) -> u8 {
+        get_bits!(self.status_flags(), 0..0)
+    }
+
+    /// Whether 63.75 m/s was sent with the speed multiplier, the only speed it is optional for
+    pub fn speed_multiplier(&self) -> bool {
+        self.speed_multiplier_bit() == 1 && get_bytes!(self.buffer, 3, 1) == 0
+    }
+
+    /// Ground speed in m/s
+    pub fn speed(&self) -> Option<f32> {
+        let speed = get_bytes!(self.buffer, 3, 1);
+        if self.speed_multiplier_bit() == 0 {
+            Some(speed as f32 * 0.25)
+        } else if speed == UNKNOWN_SPEED {
+            None
+        } else {
+            Some(speed as f32 * 0.75 + 255. * 0.25)
+        }
+    }
+
+    /// Vertical speed in m/s, positive when climbing
+    pub fn vertical_speed(&self) -> Option<f32> {
+        let vertical_speed = get_bytes!(self.buffer, 4, 1) as i8;
+        (vertical_speed != UNKNOWN_VERTICAL_SPEED).then_some(vertical_speed as f32 * 0.5)
+    }
+
+    pub fn latitude(&self) -> Option<f64> {
+        decode_coordinate(i32::from_le_bytes(get_bytes!(self.buffer, 5, 4)))
+    }
+
+    pub fn longitude(&self) -> Option<f64> {
+        decode_coordinate(i32::from_le_bytes(get_bytes!(self.buffer, 9, 4)))
+    }
+
+    pub fn pressure_altitude(&self) -> Option<f32> {
+        decode_altitude(u16::from_le_bytes(get_bytes!(self.buffer, 13, 2)))
+    }
+
+    pub fn geodetic_altitude(&self) -> Option<f32> {
+        decode_altitude(u16::from_le_bytes(get_bytes!(self.buffer, 15, 2)))
+    }
+
+    pub fn height(&self) -> Option<f32> {
+        decode_altitude(u16::from_le_bytes(get_bytes!(self.buffer, 17, 2)))
+    }
+
+    pub fn vertical_accuracy(&self) -> VerticalAccuracy {
+        VerticalAccuracy::from(get_bits!(get_bytes!(self.buffer, 19, 1), 7..4))
+    }
+
+    pub fn horizontal_accuracy(&self) -> HorizontalAccuracy {
+        HorizontalAccuracy::from(get_bits!(get_bytes!(self.buffer, 19, 1), 3..0))
+    }
+
+    pub fn baro_altitude_accuracy(&self) -> VerticalAccuracy {
+        VerticalAccuracy::from(get_bits!(get_bytes!(self.buffer, 20, 1), 7..4))
+    }
+
+    pub fn speed_accuracy(&self) -> SpeedAccuracy {
+        SpeedAccuracy::from(get_bits!(get_bytes!(self.buffer, 20, 1), 3..0))
+    }
+
+    /// Seconds since the last full hour
+    pub fn timestamp(&self) -> Result<Option<f32>, DecodeError> {
+        // 1/10th seconds since the last full hour, 0xFFFF if unknown
+        let timestamp = u16::from_le_bytes(get_bytes!(self.buffer, 21, 2));
+        if timestamp > 36000 && timestamp != UNKNOWN_TIMESTAMP {
+            return Err(DecodeError::InvalidTimestamp);
+        }
+        Ok((timestamp != UNKNOWN_TIMESTAMP).then(|| timestamp as f32 / 10.))
+    }
+
+    pub fn timestamp_accuracy(&self) -> Option<Duration> {
+        // Timestamp Accuracy: Bits [3..0]
+        let timestamp_accuracy = get_bits!(get_bytes!(self.buffer, 23, 1), 3..0);
+        (timestamp_accuracy != 0).then(|| Duration::from_millis(timestamp_accuracy as u64 * 100))
+    }
+
+    pub fn reserved(&self) -> [u8; 3] {
+        [
+            self.status_flags() & bitmask!(3, 3),
+            get_bytes!(self.buffer, 23, 1) & bitmask!(4, 7),
+            get_bytes!(self.buffer, 24, 1),
+        ]
+    }
+}
+
+impl TryFrom<LocationView<'_>> for Location {
+    type Error = DecodeError;
+
+    fn try_from(view: LocationView<'_>) -> Result<Self, Self::Error> {
+        Ok(Location {
+            operational_status: view.operational_status(),
+            height_type: view.height_type(),
+            speed_multiplier: view.speed_multiplier(),
+            speed: view.speed(),
+            vertical_speed: view.vertical_speed(),
+            pressure_altitude: view.pressure_altitude(),
+            geodetic_altitude: view.geodetic_altitude(),
+            track_direction: view.track_direction(),
+            horizontal_accuracy: view.horizontal_accuracy(),
+            vertical_accuracy: view.vertical_accuracy(),
+            latidute: view.latitude(),
+            longitude: view.longitude(),
+            height: view.height(),
+            baro_altitude_accuracy: view.baro_altitude_accuracy(),
+            speed_accuracy: view.speed_accuracy(),
+            timestamp: view.timestamp()?,
+            timestamp_accuracy: view.timestamp_accuracy(),
+            version: view.version(),
+            reserved: view.reserved(),
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct SelfIdView<'a> {
+    buffer: &'a [u8],
+    version: ProtocolVersion,
+}
+
+impl<'a> SelfIdView<'a> {
+    pub fn new(buffer: &'a [u8]) -> Result<Self, DecodeError> {
+        let version = check_message(buffer, MessageType::Selfid)?;
+        Ok(Self { buffer, version })
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    pub fn description_type(&self) -> DescriptionType {
+        DescriptionType::from(self.buffer[1])
+    }
+
+    /// ASCII text, padded with NUL
+    pub fn description(&self) -> &'a [u8; MAX_DESCRIPTION_BYTE_SIZE] {
+        array_at(self.buffer, 2)
+    }
+}
+
+impl From<SelfIdView<'_>> for SelfId {
+    fn from(view: SelfIdView<'_>) -> Self {
+        SelfId {
+            description_type: view.description_type(),
+            description: *view.description(),
+            version: view.version(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct SystemView<'a> {
+    buffer: &'a [u8],
+    version: ProtocolVersion,
+}
+
+impl<'a> SystemView<'a> {
+    pub fn new(buffer: &'a [u8]) -> Result<Self, DecodeError> {
+        let version = check_message(buffer, MessageType::System)?;
+        Ok(Self { buffer, version })
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    /// F3411-19 reserves everything after the area floor
+    fn legacy(&self) -> bool {
+        self.version == ProtocolVersion::F3411_19
+    }
+
+    // Classification Type: Bits [4..2], reserved in F3411-19
+    pub fn classification_type(&self) -> ClassificationType {
+        if self.legacy() {
+            ClassificationType::Undeclared
+        } else {
+            ClassificationType::from(get_bits!(self.buffer[1], 4..2))
+        }
+    }
+
+    // Operator Location/Altitude source type: Bits [1..0]
+    pub fn operator_location_type(&self) -> OperatorLocationType {
+        OperatorLocationType::from(get_bits!(self.buffer[1], 1..0))
+    }
+
+    pub fn operator_latitude(&self) -> Option<f64> {
+        decode_coordinate(i32::from_le_bytes(get_bytes!(self.buffer, 2, 4)))
+    }
+
+    pub fn operator_longitude(&self) -> Option<f64> {
+        decode_coordinate(i32::from_le_bytes(get_bytes!(self.buffer, 6, 4)))
+    }
+
+    /// Number of aircraft in area, group or formation
+    pub fn area_count(&self) -> u16 {
+        u16::from_le_bytes(get_bytes!(self.buffer, 10, 2))
+    }
+
+    /// Radius in m of the area, centered on the location of the aircraft
+    pub fn area_radius(&self) -> f32 {
+        get_bytes!(self.buffer, 12, 1) as f32 * 10.
+    }
+
+    pub fn area_ceiling(&self) -> Option<f32> {
+        decode_altitude(u16::from_le_bytes(get_bytes!(self.buffer, 13, 2)))
+    }
+
+    pub fn area_floor(&self) -> Option<f32> {
+        decode_altitude(u16::from_le_bytes(get_bytes!(self.buffer, 15, 2)))
+    }
+
+    // UA Classification, reserved in F3411-19
+    //   Category: Bits [7..4], Class: Bits [3..0]
+    pub fn ua_classification(&self) -> UaClassification {
+        if self.legacy() {
+            UaClassification::undefined()
+        } else {
+            UaClassification {
+                category: UaCategory::from(get_bits!(self.buffer[17], 7..4)),
+                class: UaClass::from(get_bits!(self.buffer[17], 3..0)),
+            }
+        }
+    }
+
+    pub fn operator_altitude(&self) -> Option<f32> {
+        if self.legacy() {
+            None
+        } else {
+            decode_altitude(u16::from_le_bytes(get_bytes!(self.buffer, 18, 2)))
+        }
+    }
+
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        if self.legacy() {
+            None
+        } else {
+            let unix_secs = u32::from_le_bytes(get_bytes!(self.buffer, 20, 4));
+            // 0 is unknown, any other u32 offset from 2019 is a valid date
+            (unix_secs != 0)
+                .then(|| DateTime::from_timestamp(unix_secs as i64 + REMOTE_ID_EPOCH, 0))
+                .flatten()
+        }
+    }
+
+    pub fn reserved(&self) -> [u8; 2] {
+        let flags = if self.legacy() {
+            bitmask!(2, 7)
+        } else {
+            bitmask!(5, 7)
+        };
+        [self.buffer[1] & flags, get_bytes!(self.buffer, 24, 1)]
+    }
+
+    /// Bytes 17..24 of F3411-19 messages, zero for later versions
+    pub fn legacy_reserved(&self) -> [u8; 7] {
+        if self.legacy() {
+            *array_at(self.buffer, 17)
+        } else {
+            [0; 7]
+        }
+    }
+}
+
+impl From<SystemView<'_>> for System {
+    fn from(view: SystemView<'_>) -> Self {
+        System {
+            classification_type: view.classification_type(),
+            operator_location_type: view.operator_location_type(),
+            operator_latidute: view.operator_latitude(),
+            operator_longitude: view.operator_longitude(),
+            area_count: view.area_count(),
+            area_radius: view.area_radius(),
+            area_ceiling: view.area_ceiling(),
+            area_floor: view.area_floor(),
+            ua_classification: view.ua_classification(),
+            operator_altitude: view.operator_altitude(),
+            timestamp: view.timestamp(),
+            version: view.version(),
+            reserved: view.reserved(),
+            legacy_reserved: view.legacy_reserved(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct OperatorIdView<'a> {
+    buffer: &'a [u8],
+    version: ProtocolVersion,
+}
+
+impl<'a> OperatorIdView<'a> {
+    pub fn new(buffer: &'a [u8]) -> Result<Self, DecodeError> {
+        let version = check_message(buffer, MessageType::OperatorId)?;
+        Ok(Self { buffer, version })
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        self.version
+    }
+
+    pub fn id_type(&self) -> OperatorIdType {
+        OperatorIdType::from(self.buffer[1])
+    }
+
+    /// Operator ID, padded with NUL
+    pub fn operator_id(&self) -> &'a [u8; MAX_ID_BYTE_SIZE] {
+        array_at(self.buffer, 2)
+    }
+
+    pub fn reserved(&self) -> [u8; 3] {
+        get_bytes!(self.buffer, MAX_ID_BYTE_SIZE + 2, 3)
+    }
+}
+
+impl From<OperatorIdView<'_>> for OperatorId {
+    fn from(view: OperatorIdView<'_>) -> Self {
+        OperatorId {
+            id_type: view.id_type(),
+            operator_id: *view.operator_id(),
+            version: view.version(),
+            reserved: view.reserved(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LOCATION: [u8; 25] = [
+        18, 34, 157, 0, 0, 143, 76, 186, 29, 192, 227, 79, 5, 77, 9, 116, 9, 208, 7, 91, 4, 26, 14,
+        0, 0,
+    ];
+
+    #[test]
+    fn peek_message_type() {
+        assert_eq!(Ok(MessageType::Location), message_type(&LOCATION));
+        assert_eq!(
+            Err(DecodeError::UnsupportedMessageType(6)),
+            message_type(&[0x62])
+        );
+    }
+
+    #[test]
+    fn location_view() {
+        let view = LocationView::new(&LOCATION).unwrap();
+
+        assert_eq!(OperationalStatus::Airborne, view.operational_status());
+        assert_eq!(Some(337), view.track_direction());
+        assert_eq!(Some(0.), view.speed());
+        assert_eq!(Some(49.8748559), view.latitude());
+        assert_eq!(Ok(Some(361.)), view.timestamp());
+    }
+
+    #[test]
+    fn view_checks_message_type_and_length() {
+        assert_eq!(
+            Err(DecodeError::UnexpectedMessageType(1)),
+            BasicIdView::new(&LOCATION).map(|_| ())
+        );
+        assert_eq!(
+            Err(DecodeError::BufferTooShort {
+                expected: 25,
+                actual: 24
+            }),
+            LocationView::new(&LOCATION[..24]).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn basic_id_view_borrows_uas_id() {
+        let buffer = [
+            2, 16, 49, 53, 57, 54, 70, 51, 53, 57, 55, 52, 54, 49, 54, 55, 50, 54, 48, 55, 52, 57,
+            0, 0, 0,
+        ];
+        let view = BasicIdView::new(&buffer).unwrap();
+
+        assert_eq!(IdType::SerialNumber, view.id_type());
+        assert_eq!(b"1596F359746167260749", view.uas_id_bytes());
+        assert!(core::ptr::eq(&buffer[2], &view.uas_id_bytes()[0]));
+    }
+}
@@ -0,0 +1,391 @@
+use alloc::vec::Vec;
+use chrono::{DateTime, Utc};
+
+use crate::codec::ProtocolVersion;
+
+pub const MESSAGE_TYPE: u8 = 2;
+
+/// Authentication data is split into at most 16 pages (0..=15)
+pub const MAX_PAGES: usize = 16;
+
+/// Bytes of authentication data carried by page 0
+pub const FIRST_PAGE_DATA_BYTE_SIZE: usize = 17;
+
+/// Bytes of authentication data carried by pages 1..=15
+pub const PAGE_DATA_BYTE_SIZE: usize = 23;
+
+/// The length of the authentication data is encoded in a single byte
+pub const MAX_AUTH_DATA_BYTE_SIZE: usize = u8::MAX as usize;
+
+/// Whether `length` bytes of authentication data need exactly the pages up to `last_page_index`
+pub fn is_valid_length(last_page_index: u8, length: u8) -> bool {
+    let last_page_index = last_page_index as usize;
+    let length = length as usize;
+    let capacity =
+        |last_page_index| FIRST_PAGE_DATA_BYTE_SIZE + last_page_index * PAGE_DATA_BYTE_SIZE;
+    match last_page_index {
+        0 => length <= FIRST_PAGE_DATA_BYTE_SIZE,
+        1..MAX_PAGES => {
+            capacity(last_page_index - 1) < length && length <= capacity(last_page_index)
+        }
+        _ => false,
+    }
+}
+
+/// A single page of an authentication message
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Authentication {
+    pub auth_type: AuthType,
+    pub page: AuthenticationPage,
+    /// Protocol version the message was received with, and is encoded with
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub version: ProtocolVersion,
+}
+
+impl Authentication {
+    pub fn page_number(&self) -> u8 {
+        match self.page {
+            AuthenticationPage::First { .. } => 0,
+            AuthenticationPage::Subsequent { page_number, .. } => page_number,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AuthenticationPage {
+    /// Page 0, describes the complete authentication data
+    First {
+        /// Index of the last page carrying authentication data
+        last_page_index: u8,
+        /// Total number of bytes of authentication data over all pages
+        length: u8,
+        timestamp: DateTime<Utc>,
+        data: [u8; FIRST_PAGE_DATA_BYTE_SIZE],
+    },
+
+    /// Pages 1..=15
+    Subsequent {
+        page_number: u8,
+        data: [u8; PAGE_DATA_BYTE_SIZE],
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AuthType {
+    None,
+    UasIdSignature,
+    OperatorIdSignature,
+    MessageSetSignature,
+    /// Authentication provided by Network Remote ID
+    NetworkRemoteId,
+    SpecificAuthenticationMethod,
+
+    /// 6-9: Reserved
+    Reserved(u8),
+    /// 10-15: Available for private use
+    PrivateUse(u8),
+}
+
+impl From<u8> for AuthType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => AuthType::None,
+            1 => AuthType::UasIdSignature,
+            2 => AuthType::OperatorIdSignature,
+            3 => AuthType::MessageSetSignature,
+            4 => AuthType::NetworkRemoteId,
+            5 => AuthType::SpecificAuthenticationMethod,
+            6..=9 => AuthType::Reserved(value),
+            10.. => AuthType::PrivateUse(value),
+        }
+    }
+}
+
+impl From<AuthType> for u8 {
+    fn from(val: AuthType) -> Self {
+        match val {
+            AuthType::None => 0,
+            AuthType::UasIdSignature => 1,
+            AuthType::OperatorIdSignature => 2,
+            AuthType::MessageSetSignature => 3,
+            AuthType::NetworkRemoteId => 4,
+            AuthType::SpecificAuthenticationMethod => 5,
+            AuthType::Reserved(value) => value,
+            AuthType::PrivateUse(value) => value,
+        }
+    }
+}
+
+/// Complete authentication data, reassembled from or to be split into pages
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuthenticationData {
+    pub auth_type: AuthType,
+    pub timestamp: DateTime<Utc>,
+    pub data: Vec<u8>,
+}
+
+/// Collects the authentication pages of a single sender
+///
+/// Pages may arrive in any order. Once all pages announced by page 0 were received,
+/// the complete authentication data is returned and the reassembler starts over.
+#[derive(Debug, Clone, Default)]
+pub struct AuthenticationReassembler {
+    auth_type: Option<AuthType>,
+    first_page: Option<Authentication>,
+    pages: [Option<[u8; PAGE_DATA_BYTE_SIZE]>; MAX_PAGES],
+}
+
+impl AuthenticationReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop all collected pages
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Add a page, returns the authentication data once all pages are present
+    pub fn push(&mut self, page: Authentication) -> Option<AuthenticationData> {
+        // pages of a different authentication type belong to a new authentication
+        if self.auth_type.is_some_and(|t| t != page.auth_type) {
+            self.reset();
+        }
+        self.auth_type = Some(page.auth_type);
+
+        match page.page {
+            AuthenticationPage::First { .. } => {
+                // a new first page starts a new authentication
+                if self.first_page.is_some_and(|first| first != page) {
+                    self.reset();
+                    self.auth_type = Some(page.auth_type);
+                }
+                self.first_page = Some(page);
+            }
+            AuthenticationPage::Subsequent { page_number, data } => {
+                let index = page_number as usize;
+                if index == 0 || index >= MAX_PAGES {
+                    return None;
+                }
+                self.pages[index] = Some(data);
+            }
+        }
+
+        let auth_data = self.complete()?;
+        self.reset();
+        Some(auth_data)
+    }
+
+    /// Add the pages collected by another reassembler, e.g. of the same sender under another
+    /// address, returns the authentication data if it is complete afterwards
+    ///
+    /// The pages of `other` are pushed after the own ones and take precedence.
+    pub fn merge(&mut self, other: Self) -> Option<AuthenticationData> {
+        let auth_type = other.auth_type?;
+        let subsequent = other
+            .pages
+            .into_iter()
+            .enumerate()
+            .filter_map(|(page_number, data)| {
+                Some(Authentication {
+                    auth_type,
+                    page: AuthenticationPage::Subsequent {
+                        page_number: page_number as u8,
+                        data: data?,
+                    },
+                    version: ProtocolVersion::default(),
+                })
+            });
+
+        let mut auth_data = None;
+        for page in other.first_page.into_iter().chain(subsequent) {
+            auth_data = self.push(page).or(auth_data);
+        }
+        auth_data
+    }
+
+    fn complete(&self) -> Option<AuthenticationData> {
+        let AuthenticationPage::First {
+            last_page_index,
+            length,
+            timestamp,
+            data: first_data,
+        } = self.first_page?.page
+        else {
+            return None;
+        };
+
+        // malformed first pages are rejected by the decoder, but may be constructed
+        if !is_valid_length(last_page_index, length) {
+            return None;
+        }
+        let last_page_index = last_page_index as usize;
+
+        let mut data =
+            Vec::with_capacity(FIRST_PAGE_DATA_BYTE_SIZE + last_page_index * PAGE_DATA_BYTE_SIZE);
+        data.extend_from_slice(&first_data);
+        for page in &self.pages[1..=last_page_index] {
+            data.extend_from_slice(page.as_ref()?);
+        }
+        data.truncate(length as usize);
+
+        Some(AuthenticationData {
+            auth_type: self.auth_type?,
+            timestamp,
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::DateTime;
+
+    use super::*;
+    use crate::codec::copy_to_array;
+
+    fn pages() -> [Authentication; 3] {
+        let timestamp = DateTime::parse_from_rfc3339("2024-07-04T14:05:54Z")
+            .unwrap()
+            .to_utc();
+        [
+            Authentication {
+                auth_type: AuthType::UasIdSignature,
+                page: AuthenticationPage::First {
+                    last_page_index: 2,
+                    length: 50,
+                    timestamp,
+                    data: [1; FIRST_PAGE_DATA_BYTE_SIZE],
+                },
+                version: ProtocolVersion::default(),
+            },
+            Authentication {
+                auth_type: AuthType::UasIdSignature,
+                page: AuthenticationPage::Subsequent {
+                    page_number: 1,
+                    data: [2; PAGE_DATA_BYTE_SIZE],
+                },
+                version: ProtocolVersion::default(),
+            },
+            Authentication {
+                auth_type: AuthType::UasIdSignature,
+                page: AuthenticationPage::Subsequent {
+                    page_number: 2,
+                    data: copy_to_array(&[3; 10]),
+                },
+                version: ProtocolVersion::default(),
+            },
+        ]
+    }
+
+    #[test]
+    fn reassemble_in_order() {
+        let [first, second, third] = pages();
+        let mut reassembler = AuthenticationReassembler::new();
+
+        assert_eq!(None, reassembler.push(first));
+        assert_eq!(None, reassembler.push(second));
+        let auth_data = reassembler.push(third).unwrap();
+
+        assert_eq!(AuthType::UasIdSignature, auth_data.auth_type);
+        assert_eq!(50, auth_data.data.len());
+        assert_eq!([1; 17], auth_data.data[0..17]);
+        assert_eq!([2; 23], auth_data.data[17..40]);
+        assert_eq!([3; 10], auth_data.data[40..50]);
+    }
+
+    #[test]
+    fn reassemble_out_of_order() {
+        let [first, second, third] = pages();
+        let mut reassembler = AuthenticationReassembler::new();
+
+        assert_eq!(None, reassembler.push(third));
+        assert_eq!(None, reassembler.push(first));
+        assert!(reassembler.push(second).is_some());
+
+        // reassembler starts over after completion
+        assert_eq!(None, reassembler.push(first));
+    }
+
+    #[test]
+    fn reassemble_rejects_malformed_first_page() {
+        let [mut first, second, third] = pages();
+        let AuthenticationPage::First { length, .. } = &mut first.page else {
+            unreachable!()
+        };
+        *length = 70;
+        let mut reassembler = AuthenticationReassembler::new();
+        reassembler.push(first);
+        reassembler.push(second);
+        assert_eq!(None, reassembler.push(third));
+
+        let [mut first, second, third] = pages();
+        let AuthenticationPage::First {
+            last_page_index, ..
+        } = &mut first.page
+        else {
+            unreachable!()
+        };
+        *last_page_index = 16;
+        let mut reassembler = AuthenticationReassembler::new();
+        reassembler.push(first);
+        reassembler.push(second);
+        assert_eq!(None, reassembler.push(third));
+    }
+
+    #[test]
+    fn valid_lengths() {
+        assert!(is_valid_length(0, 0));
+        assert!(is_valid_length(0, 17));
+        assert!(!is_valid_length(0, 18));
+        assert!(!is_valid_length(1, 17));
+        assert!(is_valid_length(1, 18));
+        assert!(is_valid_length(1, 40));
+        assert!(!is_valid_length(1, 41));
+        assert!(is_valid_length(11, 255));
+        assert!(!is_valid_length(12, 255));
+        assert!(!is_valid_length(16, 17));
+    }
+
+    #[test]
+    fn merge_reassemblers() {
+        let [first, second, third] = pages();
+        let mut reassembler = AuthenticationReassembler::new();
+        let mut other = AuthenticationReassembler::new();
+
+        reassembler.push(first);
+        other.push(third);
+        assert_eq!(None, reassembler.merge(other.clone()));
+        assert_eq!(50, reassembler.push(second).unwrap().data.len());
+
+        // a newer first page replaces the pages of the previous authentication
+        reassembler.push(first);
+        reassembler.push(second);
+        let mut newer = first;
+        newer.page = AuthenticationPage::First {
+            last_page_index: 1,
+            length: 20,
+            timestamp: DateTime::UNIX_EPOCH,
+            data: [4; FIRST_PAGE_DATA_BYTE_SIZE],
+        };
+        other.reset();
+        other.push(newer);
+        assert_eq!(None, reassembler.merge(other));
+        assert_eq!(None, reassembler.push(third));
+    }
+
+    #[test]
+    fn reassemble_drops_pages_of_other_auth_type() {
+        let [first, mut second, third] = pages();
+        let mut reassembler = AuthenticationReassembler::new();
+
+        reassembler.push(first);
+        second.auth_type = AuthType::OperatorIdSignature;
+        assert_eq!(None, reassembler.push(second));
+        assert_eq!(None, reassembler.push(third));
+    }
+}
@@ -1,22 +1,101 @@
-use crate::MAX_ID_BYTE_SIZE;
+use super::uas_id::{CaaRegistrationId, SerialNumber, UasId, UasIdError};
+use crate::codec::encode::{validate_basic_id, EncodeError};
+use crate::codec::ProtocolVersion;
 
 pub const MESSAGE_TYPE: u8 = 0;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BasicId {
-    pub id_type: IdType,
     pub ua_type: UAType,
-    pub uas_id: [u8; MAX_ID_BYTE_SIZE],
+    /// The ID type is given by the kind of UAS ID
+    pub uas_id: UasId,
+    /// Protocol version the message was received with, and is encoded with
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub version: ProtocolVersion,
+    /// Reserved bytes, kept to re-encode received messages unchanged
+    pub reserved: [u8; 3],
+}
+
+impl BasicId {
+    pub fn builder() -> BasicIdBuilder {
+        BasicIdBuilder::default()
+    }
+
+    pub fn id_type(&self) -> IdType {
+        self.uas_id.id_type()
+    }
+}
+
+/// Builds a [`BasicId`], without an ID and UA type by default
+#[derive(Debug, Clone)]
+pub struct BasicIdBuilder {
+    basic_id: BasicId,
+    error: Option<UasIdError>,
+}
+
+impl Default for BasicIdBuilder {
+    fn default() -> Self {
+        Self {
+            basic_id: BasicId {
+                ua_type: UAType::None,
+                uas_id: UasId::None,
+                version: ProtocolVersion::default(),
+                reserved: [0; 3],
+            },
+            error: None,
+        }
+    }
+}
+
+impl BasicIdBuilder {
+    pub fn ua_type(mut self, ua_type: UAType) -> Self {
+        self.basic_id.ua_type = ua_type;
+        self
+    }
+
+    pub fn uas_id(mut self, uas_id: UasId) -> Self {
+        self.basic_id.uas_id = uas_id;
+        self
+    }
+
+    /// ANSI/CTA-2063-A serial number, e.g. `1596F359746167260749`
+    pub fn serial_number(mut self, serial_number: &str) -> Self {
+        match SerialNumber::parse(serial_number) {
+            Ok(serial_number) => self.basic_id.uas_id = UasId::SerialNumber(serial_number),
+            Err(error) => self.error = Some(error),
+        }
+        self
+    }
+
+    pub fn caa_registration_id(mut self, registration_id: &str) -> Self {
+        match CaaRegistrationId::new(registration_id) {
+            Ok(registration_id) => self.basic_id.uas_id = UasId::CaaRegistrationId(registration_id),
+            Err(error) => self.error = Some(error),
+        }
+        self
+    }
+
+    pub fn build(self) -> Result<BasicId, EncodeError> {
+        if let Some(error) = self.error {
+            return Err(EncodeError::InvalidUasId(error));
+        }
+        validate_basic_id(&self.basic_id)?;
+        Ok(self.basic_id)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IdType {
-    None = 0,
+    None,
     /// ANSI/CTA-2063-A
-    SerialNumber = 1,
-    CaaRegistrationId = 2,
-    UtmAssignedId = 3,
-    SpecificSessionId = 4,
+    SerialNumber,
+    CaaRegistrationId,
+    UtmAssignedId,
+    SpecificSessionId,
+
+    Unknown(u8),
 }
 
 impl From<u8> for IdType {
@@ -28,7 +107,8 @@ impl From<u8> for IdType {
             3 => IdType::UtmAssignedId,
             4 => IdType::SpecificSessionId,
 
-            _ => IdType::None,
+            // 5-15: Reserved
+            5.. => IdType::Unknown(value),
         }
     }
 }
@@ -41,11 +121,13 @@ impl From<IdType> for u8 {
             IdType::CaaRegistrationId => 2,
             IdType::UtmAssignedId => 3,
             IdType::SpecificSessionId => 4,
+            IdType::Unknown(value) => value,
         }
     }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UAType {
     None = 0,
     Aeroplane = 1,
@@ -111,3 +193,41 @@ impl From<UAType> for u8 {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builder_parses_ids() {
+        let basic_id = BasicId::builder()
+            .ua_type(UAType::HelicopterOrMultirotor)
+            .serial_number("1596F359746167260749")
+            .build()
+            .unwrap();
+
+        assert_eq!(IdType::SerialNumber, basic_id.id_type());
+        assert_eq!(*b"1596F359746167260749", basic_id.uas_id.to_bytes());
+
+        let basic_id = BasicId::builder()
+            .caa_registration_id("FIN87astrdge12k8")
+            .build()
+            .unwrap();
+        assert_eq!(IdType::CaaRegistrationId, basic_id.id_type());
+        assert_eq!(*b"FIN87astrdge12k8\0\0\0\0", basic_id.uas_id.to_bytes());
+    }
+
+    #[test]
+    fn builder_rejects_malformed_ids() {
+        assert_eq!(
+            Err(EncodeError::InvalidUasId(UasIdError::TooLong)),
+            BasicId::builder()
+                .serial_number("1596F3597461672607491")
+                .build()
+        );
+        assert_eq!(
+            Err(EncodeError::InvalidUasId(UasIdError::LengthMismatch)),
+            BasicId::builder().serial_number("1596F35974").build()
+        );
+    }
+}
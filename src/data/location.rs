@@ -1,9 +1,13 @@
 use core::time::Duration;
 
+use crate::codec::encode::{validate_location, EncodeError};
+use crate::codec::ProtocolVersion;
+
 pub const MESSAGE_TYPE: u8 = 1;
 
 #[allow(non_camel_case_types)]
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VerticalAccuracy {
     /// >=150 m or Unknown
     Unknown,
@@ -13,6 +17,9 @@ pub enum VerticalAccuracy {
     LessThan_10_m,
     LessThan_3_m,
     LessThan_1_m,
+
+    /// 7-15: Reserved
+    Reserved(u8),
 }
 
 impl From<u8> for VerticalAccuracy {
@@ -25,7 +32,7 @@ impl From<u8> for VerticalAccuracy {
             4 => Self::LessThan_10_m,
             5 => Self::LessThan_3_m,
             6 => Self::LessThan_1_m,
-            7..=u8::MAX => Self::Unknown,
+            7..=u8::MAX => Self::Reserved(value),
         }
     }
 }
@@ -40,12 +47,14 @@ impl From<VerticalAccuracy> for u8 {
             VerticalAccuracy::LessThan_10_m => 4,
             VerticalAccuracy::LessThan_3_m => 5,
             VerticalAccuracy::LessThan_1_m => 6,
+            VerticalAccuracy::Reserved(value) => value,
         }
     }
 }
 
 #[allow(non_camel_case_types)]
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HorizontalAccuracy {
     /// >= 18.52 km (10 NM) or Unknown
     Unknown,
@@ -73,6 +82,9 @@ pub enum HorizontalAccuracy {
     LessThan_3_m,
     ///  <1 m
     LessThan_1_m,
+
+    /// 13-15: Reserved
+    Reserved(u8),
 }
 
 impl From<u8> for HorizontalAccuracy {
@@ -91,7 +103,7 @@ impl From<u8> for HorizontalAccuracy {
             10 => Self::LessThan_10_m,
             11 => Self::LessThan_3_m,
             12 => Self::LessThan_1_m,
-            13..=u8::MAX => Self::Unknown,
+            13..=u8::MAX => Self::Reserved(value),
         }
     }
 }
@@ -112,12 +124,14 @@ impl From<HorizontalAccuracy> for u8 {
             HorizontalAccuracy::LessThan_10_m => 10,
             HorizontalAccuracy::LessThan_3_m => 11,
             HorizontalAccuracy::LessThan_1_m => 12,
+            HorizontalAccuracy::Reserved(value) => value,
         }
     }
 }
 
 #[allow(non_camel_case_types)]
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SpeedAccuracy {
     /// >=10 m/s or Unknown
     Unknown,
@@ -129,6 +143,9 @@ pub enum SpeedAccuracy {
     LessThan_1_mps,
     /// <0.3 m/s
     LessThan_third_mps,
+
+    /// 5-15: Reserved
+    Reserved(u8),
 }
 
 impl From<u8> for SpeedAccuracy {
@@ -139,7 +156,7 @@ impl From<u8> for SpeedAccuracy {
             2 => Self::LessThan_3_mps,
             3 => Self::LessThan_1_mps,
             4 => Self::LessThan_third_mps,
-            5..=u8::MAX => Self::Unknown,
+            5..=u8::MAX => Self::Reserved(value),
         }
     }
 }
@@ -152,52 +169,205 @@ impl From<SpeedAccuracy> for u8 {
             SpeedAccuracy::LessThan_3_mps => 2,
             SpeedAccuracy::LessThan_1_mps => 3,
             SpeedAccuracy::LessThan_third_mps => 4,
+            SpeedAccuracy::Reserved(value) => value,
         }
     }
 }
 
+/// Location and vector of the UA
+///
+/// Fields are `None` if the value is unknown, which is encoded with the special value
+/// reserved by the specification.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Location {
     pub operational_status: OperationalStatus,
     pub height_type: HeightType,
     // pub ew_direction_segment: EastWestDirectionSegment,
-    /// Speed Multiplier enables speeds up to 254.25 m/s. Only use 1 when speed exceeds 63.75 m/s and add 63.75.
-    // pub speed_multiplier: f32,
-    pub speed: f32,
-    pub vertical_speed: f32,
-    pub pressure_altitude: f32,
-    pub geodetic_altitude: f32,
+    /// Speed Multiplier enables speeds up to 254.25 m/s and is always used above 63.75 m/s.
+    /// 63.75 m/s itself can be encoded with and without it, this keeps the received choice.
+    pub speed_multiplier: bool,
+    /// Ground speed in m/s, encoded as 255 m/s if unknown
+    pub speed: Option<f32>,
+    /// Vertical speed in m/s, positive when climbing, encoded as 63 m/s if unknown
+    pub vertical_speed: Option<f32>,
+    /// Encoded as -1000 m if unknown
+    pub pressure_altitude: Option<f32>,
+    /// Encoded as -1000 m if unknown
+    pub geodetic_altitude: Option<f32>,
     /// Direction expressed as the route course measured clockwise from true north.
-    pub track_direction: u16,
+    /// Encoded as 361 if unknown
+    pub track_direction: Option<u16>,
     pub horizontal_accuracy: HorizontalAccuracy,
     pub vertical_accuracy: VerticalAccuracy,
-    pub latidute: f32,
-    pub longitude: f32,
-    pub height: f32,
+    /// Encoded as 0 if unknown
+    pub latidute: Option<f64>,
+    /// Encoded as 0 if unknown
+    pub longitude: Option<f64>,
+    /// Encoded as -1000 m if unknown
+    pub height: Option<f32>,
     pub baro_altitude_accuracy: VerticalAccuracy,
     pub speed_accuracy: SpeedAccuracy,
-    pub timestamp: f32,
+    /// Seconds since the last full hour, encoded as 0xFFFF if unknown
+    pub timestamp: Option<f32>,
     pub timestamp_accuracy: Option<Duration>,
+    /// Protocol version the message was received with, and is encoded with
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub version: ProtocolVersion,
+    /// Reserved bits in place, kept to re-encode received messages unchanged:
+    /// bit 3 of the status flags, the upper nibble of the timestamp accuracy and the last byte
+    pub reserved: [u8; 3],
+}
+
+impl Location {
+    /// Start a location with every field set to its unknown value
+    pub fn builder() -> LocationBuilder {
+        LocationBuilder::default()
+    }
+}
+
+/// Builds a [`Location`], fields not set are encoded as unknown
+#[derive(Debug, Clone)]
+pub struct LocationBuilder {
+    location: Location,
+}
+
+impl Default for LocationBuilder {
+    fn default() -> Self {
+        Self {
+            location: Location {
+                operational_status: OperationalStatus::Undeclared,
+                height_type: HeightType::AboveTakeoff,
+                speed_multiplier: false,
+                speed: None,
+                vertical_speed: None,
+                pressure_altitude: None,
+                geodetic_altitude: None,
+                track_direction: None,
+                horizontal_accuracy: HorizontalAccuracy::Unknown,
+                vertical_accuracy: VerticalAccuracy::Unknown,
+                latidute: None,
+                longitude: None,
+                height: None,
+                baro_altitude_accuracy: VerticalAccuracy::Unknown,
+                speed_accuracy: SpeedAccuracy::Unknown,
+                timestamp: None,
+                timestamp_accuracy: None,
+                version: ProtocolVersion::default(),
+                reserved: [0; 3],
+            },
+        }
+    }
+}
+
+impl LocationBuilder {
+    pub fn operational_status(mut self, operational_status: OperationalStatus) -> Self {
+        self.location.operational_status = operational_status;
+        self
+    }
+
+    /// Latitude and longitude in degrees
+    pub fn position(mut self, latitude: f64, longitude: f64) -> Self {
+        self.location.latidute = Some(latitude);
+        self.location.longitude = Some(longitude);
+        self
+    }
+
+    /// Ground speed in m/s
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.location.speed = Some(speed);
+        self
+    }
+
+    /// Vertical speed in m/s, positive when climbing
+    pub fn vertical_speed(mut self, vertical_speed: f32) -> Self {
+        self.location.vertical_speed = Some(vertical_speed);
+        self
+    }
+
+    /// Degrees clockwise from true north
+    pub fn track_direction(mut self, track_direction: u16) -> Self {
+        self.location.track_direction = Some(track_direction);
+        self
+    }
+
+    pub fn pressure_altitude(mut self, pressure_altitude: f32) -> Self {
+        self.location.pressure_altitude = Some(pressure_altitude);
+        self
+    }
+
+    pub fn geodetic_altitude(mut self, geodetic_altitude: f32) -> Self {
+        self.location.geodetic_altitude = Some(geodetic_altitude);
+        self
+    }
+
+    pub fn height(mut self, height: f32, height_type: HeightType) -> Self {
+        self.location.height = Some(height);
+        self.location.height_type = height_type;
+        self
+    }
+
+    pub fn horizontal_accuracy(mut self, horizontal_accuracy: HorizontalAccuracy) -> Self {
+        self.location.horizontal_accuracy = horizontal_accuracy;
+        self
+    }
+
+    pub fn vertical_accuracy(mut self, vertical_accuracy: VerticalAccuracy) -> Self {
+        self.location.vertical_accuracy = vertical_accuracy;
+        self
+    }
+
+    pub fn baro_altitude_accuracy(mut self, baro_altitude_accuracy: VerticalAccuracy) -> Self {
+        self.location.baro_altitude_accuracy = baro_altitude_accuracy;
+        self
+    }
+
+    pub fn speed_accuracy(mut self, speed_accuracy: SpeedAccuracy) -> Self {
+        self.location.speed_accuracy = speed_accuracy;
+        self
+    }
+
+    /// Seconds since the last full hour
+    pub fn timestamp(mut self, timestamp: f32) -> Self {
+        self.location.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn timestamp_accuracy(mut self, timestamp_accuracy: Duration) -> Self {
+        self.location.timestamp_accuracy = Some(timestamp_accuracy);
+        self
+    }
+
+    /// Check all values are within the ranges the encoding can carry
+    pub fn build(self) -> Result<Location, EncodeError> {
+        validate_location(&self.location)?;
+        Ok(self.location)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OperationalStatus {
     Undeclared,
     Ground,
     Airborne,
     Emergency,
     RemoteIdSystemFailure,
+
+    Unknown(u8),
 }
 
 impl From<u8> for OperationalStatus {
     fn from(value: u8) -> Self {
         match value {
+            0 => OperationalStatus::Undeclared,
             1 => OperationalStatus::Ground,
             2 => OperationalStatus::Airborne,
             3 => OperationalStatus::Emergency,
             4 => OperationalStatus::RemoteIdSystemFailure,
 
-            _ => OperationalStatus::Undeclared,
+            // 5-15: Reserved
+            _ => OperationalStatus::Unknown(value),
         }
     }
 }
@@ -211,11 +381,13 @@ impl From<OperationalStatus> for u8 {
             OperationalStatus::RemoteIdSystemFailure => 4,
 
             OperationalStatus::Undeclared => 0,
+            OperationalStatus::Unknown(value) => value,
         }
     }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HeightType {
     AboveTakeoff,
     AboveGroundLevel,
@@ -266,3 +438,51 @@ impl From<HeightType> for u8 {
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn builder_defaults_to_unknown() {
+        let location = Location::builder().build().unwrap();
+
+        assert_eq!(OperationalStatus::Undeclared, location.operational_status);
+        assert_eq!(None, location.latidute);
+        assert_eq!(None, location.speed);
+        assert_eq!(None, location.track_direction);
+        assert_eq!(None, location.timestamp);
+        assert_eq!(HorizontalAccuracy::Unknown, location.horizontal_accuracy);
+    }
+
+    #[test]
+    fn builder_sets_fields() {
+        let location = Location::builder()
+            .operational_status(OperationalStatus::Airborne)
+            .position(49.8748544, 8.9121736)
+            .speed(12.5)
+            .height(30., HeightType::AboveGroundLevel)
+            .build()
+            .unwrap();
+
+        assert_eq!(Some(49.8748544), location.latidute);
+        assert_eq!(Some(8.9121736), location.longitude);
+        assert_eq!(Some(12.5), location.speed);
+        assert_eq!(Some(30.), location.height);
+        assert_eq!(HeightType::AboveGroundLevel, location.height_type);
+    }
+
+    #[test]
+    fn builder_validates_ranges() {
+        assert_eq!(
+            Err(EncodeError::OutOfRange { field: "latitude" }),
+            Location::builder().position(91., 0.).build()
+        );
+        assert_eq!(
+            Err(EncodeError::OutOfRange {
+                field: "track_direction"
+            }),
+            Location::builder().track_direction(360).build()
+        );
+    }
+}
@@ -0,0 +1,15 @@
+pub const MESSAGE_TYPE: u8 = 0xF;
+
+/// Size of every single message within a message pack
+pub const MESSAGE_BYTE_SIZE: usize = 25;
+
+/// Maximum number of messages in a single message pack
+pub const MAX_MESSAGES: usize = 9;
+
+/// Message type, version, message size and message count
+pub const HEADER_BYTE_SIZE: usize = 3;
+
+/// Size of a message pack carrying the given number of messages
+pub const fn byte_size(message_count: usize) -> usize {
+    HEADER_BYTE_SIZE + message_count * MESSAGE_BYTE_SIZE
+}
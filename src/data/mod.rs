@@ -1,9 +1,20 @@
+use alloc::vec::Vec;
+
+use crate::codec::ProtocolVersion;
+
+pub mod authentication;
 pub mod basic_id;
 pub mod location;
+pub mod message_pack;
 pub mod operator_id;
+pub mod self_id;
+#[cfg(feature = "serde")]
+mod serde_id;
 pub mod system;
+pub mod uas_id;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RemoteIDMessage {
     /// Provides ID for UA, characterizes the type of ID, and identifies the type of UA
     BasicID(basic_id::BasicId),
@@ -11,14 +22,39 @@ pub enum RemoteIDMessage {
     /// Provides location, altitude, direction, and speed of UA
     Location(location::Location),
 
-    // /// Provides authentication data for the UA
-    // Authentication,
+    /// Provides authentication data for the UA
+    Authentication(authentication::Authentication),
+    /// Message that can be used by Operators to identify themselves and the purpose of an operation
+    SelfId(self_id::SelfId),
 
-    // /// Message that can be used by Operators to identify themselves and the purpose of an operation
-    // SelfId,
     /// Includes Remote Pilot location and multiple aircraft information (group) if applicable, and additional system information
     System(system::System),
 
     /// Provides Operator ID
     OperatorId(operator_id::OperatorId),
+
+    /// Multiple messages transmitted together, used by Bluetooth 5 long range and Wi-Fi
+    MessagePack(Vec<RemoteIDMessage>),
+}
+
+impl RemoteIDMessage {
+    /// Protocol version the message is encoded with
+    ///
+    /// Message packs carry no version of their own and take the latest version of their
+    /// messages.
+    pub fn version(&self) -> ProtocolVersion {
+        match self {
+            RemoteIDMessage::BasicID(basic_id) => basic_id.version,
+            RemoteIDMessage::Location(location) => location.version,
+            RemoteIDMessage::Authentication(authentication) => authentication.version,
+            RemoteIDMessage::SelfId(self_id) => self_id.version,
+            RemoteIDMessage::System(system) => system.version,
+            RemoteIDMessage::OperatorId(operator_id) => operator_id.version,
+            RemoteIDMessage::MessagePack(messages) => messages
+                .iter()
+                .map(RemoteIDMessage::version)
+                .max()
+                .unwrap_or_default(),
+        }
+    }
 }
@@ -1,12 +1,96 @@
+use core::fmt;
+
+use crate::codec::copy_to_id;
+use crate::codec::encode::EncodeError;
+use crate::codec::ProtocolVersion;
+use crate::MAX_ID_BYTE_SIZE;
+
 pub const MESSAGE_TYPE: u8 = 5;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OperatorId {
     pub id_type: OperatorIdType,
-    pub operator_id: [u8; 20],
+    #[cfg_attr(feature = "serde", serde(with = "super::serde_id"))]
+    pub operator_id: [u8; MAX_ID_BYTE_SIZE],
+    /// Protocol version the message was received with, and is encoded with
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub version: ProtocolVersion,
+    /// Reserved bytes, kept to re-encode received messages unchanged
+    pub reserved: [u8; 3],
+}
+
+impl OperatorId {
+    pub fn builder() -> OperatorIdBuilder {
+        OperatorIdBuilder::default()
+    }
+
+    /// Interpret the operator ID as an EU operator registration number
+    pub fn eu_operator_id(&self) -> Result<EuOperatorId, EuOperatorIdError> {
+        let len = self
+            .operator_id
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(MAX_ID_BYTE_SIZE);
+        if self.operator_id[len..].iter().any(|&b| b != 0) {
+            return Err(EuOperatorIdError::InvalidLength);
+        }
+        let operator_id = core::str::from_utf8(&self.operator_id[..len])
+            .map_err(|_| EuOperatorIdError::InvalidCharacter)?;
+        EuOperatorId::parse(operator_id)
+    }
+}
+
+/// Builds an [`OperatorId`] of type [`OperatorIdType::OperatorId`] by default
+#[derive(Debug, Clone)]
+pub struct OperatorIdBuilder {
+    operator_id: OperatorId,
+    operator_id_len: usize,
+}
+
+impl Default for OperatorIdBuilder {
+    fn default() -> Self {
+        Self {
+            operator_id: OperatorId {
+                id_type: OperatorIdType::OperatorId,
+                operator_id: [0; MAX_ID_BYTE_SIZE],
+                version: ProtocolVersion::default(),
+                reserved: [0; 3],
+            },
+            operator_id_len: 0,
+        }
+    }
+}
+
+impl OperatorIdBuilder {
+    pub fn id_type(mut self, id_type: OperatorIdType) -> Self {
+        self.operator_id.id_type = id_type;
+        self
+    }
+
+    /// At most 20 bytes, padded with NUL
+    pub fn operator_id(mut self, operator_id: &str) -> Self {
+        self.operator_id.operator_id = copy_to_id(operator_id.as_bytes());
+        self.operator_id_len = operator_id.len();
+        self
+    }
+
+    pub fn eu_operator_id(self, operator_id: EuOperatorId) -> Self {
+        self.operator_id(operator_id.as_str())
+    }
+
+    pub fn build(self) -> Result<OperatorId, EncodeError> {
+        if self.operator_id_len > MAX_ID_BYTE_SIZE {
+            return Err(EncodeError::OutOfRange {
+                field: "operator_id",
+            });
+        }
+        Ok(self.operator_id)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OperatorIdType {
     OperatorId,
 
@@ -33,3 +117,204 @@ impl From<OperatorIdType> for u8 {
         }
     }
 }
+
+/// EU operator registration number according to EN 4709-002
+///
+/// The registration number consists of the country code of the registering state, 12 base
+/// characters, a checksum character and 3 secret characters, e.g. `FIN87astrdge12k8-xyz`.
+/// Only the first 16 characters are broadcast, the checksum covers the base and the secret
+/// characters and can only be verified if the secret is known.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct EuOperatorId([u8; EuOperatorId::BROADCAST_LEN]);
+
+impl EuOperatorId {
+    pub const COUNTRY_CODE_LEN: usize = 3;
+    pub const BASE_LEN: usize = 12;
+    pub const SECRET_LEN: usize = 3;
+    /// Country code, base characters and checksum
+    pub const BROADCAST_LEN: usize = Self::COUNTRY_CODE_LEN + Self::BASE_LEN + 1;
+
+    /// ISO 3166-1 alpha-3 codes of the EU member states and the EASA member states outside the EU
+    const COUNTRY_CODES: [&'static str; 31] = [
+        "AUT", "BEL", "BGR", "CHE", "CYP", "CZE", "DEU", "DNK", "ESP", "EST", "FIN", "FRA", "GRC",
+        "HRV", "HUN", "IRL", "ISL", "ITA", "LIE", "LTU", "LUX", "LVA", "MLT", "NLD", "NOR", "POL",
+        "PRT", "ROU", "SVK", "SVN", "SWE",
+    ];
+
+    /// Produce the broadcast form from the registration number and its secret
+    ///
+    /// The registration number may be given with or without the secret appended after a `-`,
+    /// the checksum is verified against the secret in both cases.
+    pub fn new(registration_number: &str, secret: &str) -> Result<Self, EuOperatorIdError> {
+        let registration_number = match registration_number.split_once('-') {
+            Some((registration_number, appended)) if appended == secret => registration_number,
+            Some(_) => return Err(EuOperatorIdError::InvalidChecksum),
+            None => registration_number,
+        };
+
+        let operator_id = Self::parse(registration_number)?;
+        operator_id.verify(secret)?;
+        Ok(operator_id)
+    }
+
+    /// Parse the broadcast form, the checksum can not be verified without the secret
+    pub fn parse(operator_id: &str) -> Result<Self, EuOperatorIdError> {
+        if operator_id.len() != Self::BROADCAST_LEN {
+            return Err(EuOperatorIdError::InvalidLength);
+        }
+        let operator_id: [u8; Self::BROADCAST_LEN] = operator_id
+            .as_bytes()
+            .try_into()
+            .expect("length checked above");
+
+        let country_code = &operator_id[..Self::COUNTRY_CODE_LEN];
+        if !Self::COUNTRY_CODES
+            .iter()
+            .any(|code| code.as_bytes() == country_code)
+        {
+            return Err(EuOperatorIdError::InvalidCountryCode);
+        }
+        if !operator_id[Self::COUNTRY_CODE_LEN..]
+            .iter()
+            .all(|&c| checksum_value(c).is_some())
+        {
+            return Err(EuOperatorIdError::InvalidCharacter);
+        }
+
+        Ok(Self(operator_id))
+    }
+
+    /// Verify the checksum against the secret characters
+    pub fn verify(&self, secret: &str) -> Result<(), EuOperatorIdError> {
+        if secret.len() != Self::SECRET_LEN {
+            return Err(EuOperatorIdError::InvalidLength);
+        }
+
+        let base = &self.0[Self::COUNTRY_CODE_LEN..Self::BROADCAST_LEN - 1];
+        let checksum = luhn_mod_36(base.iter().chain(secret.as_bytes()))
+            .ok_or(EuOperatorIdError::InvalidCharacter)?;
+
+        if checksum != self.0[Self::BROADCAST_LEN - 1] {
+            return Err(EuOperatorIdError::InvalidChecksum);
+        }
+        Ok(())
+    }
+
+    pub fn country_code(&self) -> &str {
+        &self.as_str()[..Self::COUNTRY_CODE_LEN]
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.0).expect("validated on construction")
+    }
+}
+
+impl fmt::Debug for EuOperatorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EuOperatorId").field(&self.as_str()).finish()
+    }
+}
+
+impl fmt::Display for EuOperatorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EuOperatorIdError {
+    /// The operator ID is not 16 characters long or the secret is not 3 characters long
+    InvalidLength,
+    /// The country code is not the code of an EU or EASA member state
+    InvalidCountryCode,
+    /// Only digits and lower case letters are allowed after the country code
+    InvalidCharacter,
+    /// The checksum does not match the base and secret characters
+    InvalidChecksum,
+}
+
+impl fmt::Display for EuOperatorIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EuOperatorIdError::InvalidLength => write!(f, "invalid length"),
+            EuOperatorIdError::InvalidCountryCode => write!(f, "invalid country code"),
+            EuOperatorIdError::InvalidCharacter => write!(f, "invalid character"),
+            EuOperatorIdError::InvalidChecksum => write!(f, "invalid checksum"),
+        }
+    }
+}
+
+impl core::error::Error for EuOperatorIdError {}
+
+const CHECKSUM_ALPHABET: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+fn checksum_value(c: u8) -> Option<u32> {
+    CHECKSUM_ALPHABET
+        .iter()
+        .position(|&a| a == c)
+        .map(|value| value as u32)
+}
+
+/// Luhn mod N algorithm over digits and lower case letters
+fn luhn_mod_36<'a>(chars: impl DoubleEndedIterator<Item = &'a u8>) -> Option<u8> {
+    let mut factor = 2;
+    let mut sum = 0;
+    for &c in chars.rev() {
+        let addend = factor * checksum_value(c)?;
+        factor = if factor == 2 { 1 } else { 2 };
+        sum += addend / 36 + addend % 36;
+    }
+    Some(CHECKSUM_ALPHABET[((36 - sum % 36) % 36) as usize])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn eu_operator_id_from_registration_number() {
+        let operator_id = EuOperatorId::new("FIN87astrdge12k8", "xyz").unwrap();
+
+        assert_eq!("FIN87astrdge12k8", operator_id.as_str());
+        assert_eq!("FIN", operator_id.country_code());
+        assert_eq!(
+            Ok(operator_id),
+            EuOperatorId::new("FIN87astrdge12k8-xyz", "xyz")
+        );
+    }
+
+    #[test]
+    fn eu_operator_id_rejects_typos() {
+        assert_eq!(
+            Err(EuOperatorIdError::InvalidChecksum),
+            EuOperatorId::new("FIN87astrdge13k8", "xyz")
+        );
+        assert_eq!(
+            Err(EuOperatorIdError::InvalidChecksum),
+            EuOperatorId::new("FIN87astrdge12k8", "xzy")
+        );
+        assert_eq!(
+            Err(EuOperatorIdError::InvalidCountryCode),
+            EuOperatorId::parse("USA87astrdge12k8")
+        );
+        assert_eq!(
+            Err(EuOperatorIdError::InvalidCharacter),
+            EuOperatorId::parse("FIN87ASTRDGE12K8")
+        );
+        assert_eq!(
+            Err(EuOperatorIdError::InvalidLength),
+            EuOperatorId::parse("FIN87astrdge12k")
+        );
+    }
+
+    #[test]
+    fn operator_id_as_eu_operator_id() {
+        let operator_id = OperatorId::builder()
+            .eu_operator_id(EuOperatorId::new("FIN87astrdge12k8", "xyz").unwrap())
+            .build()
+            .unwrap();
+
+        let eu_operator_id = operator_id.eu_operator_id().unwrap();
+        assert_eq!(Ok(()), eu_operator_id.verify("xyz"));
+    }
+}
@@ -0,0 +1,105 @@
+use crate::codec::copy_to_array;
+use crate::codec::encode::EncodeError;
+use crate::codec::ProtocolVersion;
+
+pub const MESSAGE_TYPE: u8 = 3;
+
+pub const MAX_DESCRIPTION_BYTE_SIZE: usize = 23;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SelfId {
+    pub description_type: DescriptionType,
+    /// ASCII text describing the purpose of the operation, padded with NUL
+    #[cfg_attr(feature = "serde", serde(with = "super::serde_id"))]
+    pub description: [u8; MAX_DESCRIPTION_BYTE_SIZE],
+    /// Protocol version the message was received with, and is encoded with
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub version: ProtocolVersion,
+}
+
+impl SelfId {
+    pub fn builder() -> SelfIdBuilder {
+        SelfIdBuilder::default()
+    }
+}
+
+/// Builds a [`SelfId`] with an empty text description by default
+#[derive(Debug, Clone)]
+pub struct SelfIdBuilder {
+    self_id: SelfId,
+    description_len: usize,
+}
+
+impl Default for SelfIdBuilder {
+    fn default() -> Self {
+        Self {
+            self_id: SelfId {
+                description_type: DescriptionType::Text,
+                description: [0; MAX_DESCRIPTION_BYTE_SIZE],
+                version: ProtocolVersion::default(),
+            },
+            description_len: 0,
+        }
+    }
+}
+
+impl SelfIdBuilder {
+    pub fn description_type(mut self, description_type: DescriptionType) -> Self {
+        self.self_id.description_type = description_type;
+        self
+    }
+
+    /// At most 23 bytes, padded with NUL
+    pub fn description(mut self, description: &str) -> Self {
+        self.self_id.description = copy_to_array(description.as_bytes());
+        self.description_len = description.len();
+        self
+    }
+
+    pub fn build(self) -> Result<SelfId, EncodeError> {
+        if self.description_len > MAX_DESCRIPTION_BYTE_SIZE {
+            return Err(EncodeError::OutOfRange {
+                field: "description",
+            });
+        }
+        Ok(self.self_id)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DescriptionType {
+    Text,
+    Emergency,
+    ExtendedStatus,
+
+    /// 3-200: Reserved
+    Reserved(u8),
+    /// 201-255: Available for private use
+    PrivateUse(u8),
+}
+
+impl From<u8> for DescriptionType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => DescriptionType::Text,
+            1 => DescriptionType::Emergency,
+            2 => DescriptionType::ExtendedStatus,
+            3..=200 => DescriptionType::Reserved(value),
+            201.. => DescriptionType::PrivateUse(value),
+        }
+    }
+}
+
+impl From<DescriptionType> for u8 {
+    fn from(val: DescriptionType) -> Self {
+        match val {
+            DescriptionType::Text => 0,
+            DescriptionType::Emergency => 1,
+            DescriptionType::ExtendedStatus => 2,
+            DescriptionType::Reserved(value) => value,
+            DescriptionType::PrivateUse(value) => value,
+        }
+    }
+}
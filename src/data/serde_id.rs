@@ -0,0 +1,128 @@
+//! Serialize NUL padded IDs and descriptions as trimmed strings
+//!
+//! IDs that are not valid UTF-8 fall back to their bytes, without the padding.
+
+use core::fmt;
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserializer, Serializer};
+
+use crate::codec::copy_to_array;
+
+pub fn serialize<S: Serializer, const N: usize>(
+    id: &[u8; N],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let len = id.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    match core::str::from_utf8(&id[..len]) {
+        Ok(id) => serializer.serialize_str(id),
+        Err(_) => serializer.serialize_bytes(&id[..len]),
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    deserializer.deserialize_bytes(IdVisitor::<N>)
+}
+
+struct IdVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for IdVisitor<N> {
+    type Value = [u8; N];
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string or bytes of at most {N} bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.visit_bytes(v.as_bytes())
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        if v.len() > N {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        Ok(copy_to_array(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut id = [0; N];
+        let mut len = 0;
+        while let Some(byte) = seq.next_element()? {
+            if len == N {
+                return Err(de::Error::invalid_length(len + 1, &self));
+            }
+            id[len] = byte;
+            len += 1;
+        }
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::format;
+    use std::string::String;
+
+    use crate::codec::{copy_to_id, ProtocolVersion};
+    use crate::data::basic_id::{BasicId, UAType};
+    use crate::data::location::Location;
+    use crate::data::uas_id::{SerialNumber, UasId};
+    use crate::data::RemoteIDMessage;
+
+    #[test]
+    fn basic_id_as_trimmed_string() {
+        let basic_id = RemoteIDMessage::BasicID(BasicId {
+            ua_type: UAType::HelicopterOrMultirotor,
+            uas_id: UasId::SerialNumber(SerialNumber::parse("1596F359746167260749").unwrap()),
+            reserved: [0; 3],
+            version: ProtocolVersion::F3411_22a,
+        });
+
+        let json = serde_json::to_string(&basic_id).unwrap();
+        assert_eq!(
+            r#"{"BasicID":{"ua_type":"HelicopterOrMultirotor","uas_id":{"SerialNumber":"1596F359746167260749"},"version":"F3411_22a","reserved":[0,0,0]}}"#,
+            json
+        );
+        assert_eq!(basic_id, serde_json::from_str(&json).unwrap());
+    }
+
+    #[test]
+    fn short_id_is_padded_again() {
+        let json = r#"{"ua_type":"None","uas_id":{"CaaRegistrationId":"123"},"reserved":[0,0,0]}"#;
+        let basic_id: BasicId = serde_json::from_str(json).unwrap();
+        assert_eq!(copy_to_id(b"123"), basic_id.uas_id.to_bytes());
+
+        let too_long = String::from(json).replace("123", "123456789012345678901");
+        assert!(serde_json::from_str::<BasicId>(&too_long).is_err());
+    }
+
+    #[test]
+    fn malformed_id_is_rejected() {
+        for id in [r#""ab""#, r#""1596G359746167260749""#, "[255,254]"] {
+            let json = format!(r#"{{"SerialNumber":{id}}}"#);
+            assert!(serde_json::from_str::<UasId>(&json).is_err(), "{json}");
+        }
+        for id in ["\"\"", r#""12 3""#, "[255,254]"] {
+            let json = format!(r#"{{"CaaRegistrationId":{id}}}"#);
+            assert!(serde_json::from_str::<UasId>(&json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn location_roundtrip() {
+        let location = RemoteIDMessage::Location(
+            Location::builder()
+                .position(49.8748544, 8.9121736)
+                .speed(12.5)
+                .build()
+                .unwrap(),
+        );
+
+        let json = serde_json::to_string(&location).unwrap();
+        assert_eq!(location, serde_json::from_str(&json).unwrap());
+    }
+}
@@ -1,26 +1,143 @@
 use chrono::{DateTime, Utc};
 
+use crate::codec::encode::{validate_system, EncodeError};
+use crate::codec::ProtocolVersion;
+
 pub const MESSAGE_TYPE: u8 = 4;
 
+/// Operator location and group information
+///
+/// Fields are `None` if the value is unknown, which is encoded with the special value
+/// reserved by the specification.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct System {
     pub classification_type: ClassificationType,
     pub operator_location_type: OperatorLocationType,
-    pub operator_latidute: f32,
-    pub operator_longitude: f32,
+    /// Encoded as 0 if unknown
+    pub operator_latidute: Option<f64>,
+    /// Encoded as 0 if unknown
+    pub operator_longitude: Option<f64>,
     pub area_count: u16,
     pub area_radius: f32,
-    pub area_ceiling: f32,
-    pub area_floor: f32,
+    /// Encoded as -1000 m if unknown
+    pub area_ceiling: Option<f32>,
+    /// Encoded as -1000 m if unknown
+    pub area_floor: Option<f32>,
     pub ua_classification: UaClassification,
-    pub operator_altitude: f32,
-    pub timestamp: DateTime<Utc>,
+    /// Encoded as -1000 m if unknown
+    pub operator_altitude: Option<f32>,
+    /// Not transmitted before ASTM F3411-20, encoded as 0 if unknown
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Protocol version the message was received with, and is encoded with
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub version: ProtocolVersion,
+    /// Reserved bits in place, kept to re-encode received messages unchanged:
+    /// bits 7..5 of the flags and the last byte, with F3411-19 also bits 4..2 of the flags
+    pub reserved: [u8; 2],
+    /// Bytes 17..24, reserved by F3411-19 and only encoded with that version
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub legacy_reserved: [u8; 7],
+}
+
+impl System {
+    /// Start a system message with every field set to its unknown or default value
+    pub fn builder() -> SystemBuilder {
+        SystemBuilder::default()
+    }
+}
+
+/// Builds a [`System`], fields not set are encoded as unknown
+#[derive(Debug, Clone)]
+pub struct SystemBuilder {
+    system: System,
+}
+
+impl Default for SystemBuilder {
+    fn default() -> Self {
+        Self {
+            system: System {
+                classification_type: ClassificationType::Undeclared,
+                operator_location_type: OperatorLocationType::TakeOff,
+                operator_latidute: None,
+                operator_longitude: None,
+                // a single aircraft without a group area
+                area_count: 1,
+                area_radius: 0.,
+                area_ceiling: None,
+                area_floor: None,
+                ua_classification: UaClassification::undefined(),
+                operator_altitude: None,
+                timestamp: None,
+                version: ProtocolVersion::default(),
+                reserved: [0; 2],
+                legacy_reserved: [0; 7],
+            },
+        }
+    }
+}
+
+impl SystemBuilder {
+    pub fn operator_location_type(mut self, operator_location_type: OperatorLocationType) -> Self {
+        self.system.operator_location_type = operator_location_type;
+        self
+    }
+
+    /// Operator latitude and longitude in degrees
+    pub fn operator_position(mut self, latitude: f64, longitude: f64) -> Self {
+        self.system.operator_latidute = Some(latitude);
+        self.system.operator_longitude = Some(longitude);
+        self
+    }
+
+    pub fn operator_altitude(mut self, operator_altitude: f32) -> Self {
+        self.system.operator_altitude = Some(operator_altitude);
+        self
+    }
+
+    /// Number of aircraft and radius in m of the area of a group or formation
+    pub fn area(mut self, area_count: u16, area_radius: f32) -> Self {
+        self.system.area_count = area_count;
+        self.system.area_radius = area_radius;
+        self
+    }
+
+    pub fn area_ceiling(mut self, area_ceiling: f32) -> Self {
+        self.system.area_ceiling = Some(area_ceiling);
+        self
+    }
+
+    pub fn area_floor(mut self, area_floor: f32) -> Self {
+        self.system.area_floor = Some(area_floor);
+        self
+    }
+
+    /// Sets the classification type to European Union
+    pub fn eu_classification(mut self, category: UaCategory, class: UaClass) -> Self {
+        self.system.classification_type = ClassificationType::EuropeanUnion;
+        self.system.ua_classification = UaClassification { category, class };
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.system.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Check all values are within the ranges the encoding can carry
+    pub fn build(self) -> Result<System, EncodeError> {
+        validate_system(&self.system)?;
+        Ok(self.system)
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ClassificationType {
-    Undeclared = 0,
-    EuropeanUnion = 1,
+    Undeclared,
+    EuropeanUnion,
+
+    Unknown(u8),
 }
 
 impl From<u8> for ClassificationType {
@@ -30,16 +147,29 @@ impl From<u8> for ClassificationType {
             1 => ClassificationType::EuropeanUnion,
 
             // reserved classifications
-            2.. => ClassificationType::Undeclared,
+            2.. => ClassificationType::Unknown(value),
+        }
+    }
+}
+
+impl From<ClassificationType> for u8 {
+    fn from(val: ClassificationType) -> Self {
+        match val {
+            ClassificationType::Undeclared => 0,
+            ClassificationType::EuropeanUnion => 1,
+            ClassificationType::Unknown(value) => value,
         }
     }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OperatorLocationType {
-    TakeOff = 0,
-    Dynamic = 1,
-    Fixed = 2,
+    TakeOff,
+    Dynamic,
+    Fixed,
+
+    Unknown(u8),
 }
 
 impl From<u8> for OperatorLocationType {
@@ -49,12 +179,24 @@ impl From<u8> for OperatorLocationType {
             1 => OperatorLocationType::Dynamic,
             2 => OperatorLocationType::Fixed,
 
-            3.. => OperatorLocationType::TakeOff,
+            3.. => OperatorLocationType::Unknown(value),
+        }
+    }
+}
+
+impl From<OperatorLocationType> for u8 {
+    fn from(val: OperatorLocationType) -> Self {
+        match val {
+            OperatorLocationType::TakeOff => 0,
+            OperatorLocationType::Dynamic => 1,
+            OperatorLocationType::Fixed => 2,
+            OperatorLocationType::Unknown(value) => value,
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UaClassification {
     pub category: UaCategory,
     pub class: UaClass,
@@ -70,11 +212,14 @@ impl UaClassification {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UaCategory {
     Undefined,
     Open,
     Specific,
     Certified,
+
+    Unknown(u8),
 }
 
 impl From<u8> for UaCategory {
@@ -85,7 +230,8 @@ impl From<u8> for UaCategory {
             2 => UaCategory::Specific,
             3 => UaCategory::Certified,
 
-            _ => UaCategory::Undefined,
+            // 4-15: Reserved
+            _ => UaCategory::Unknown(value),
         }
     }
 }
@@ -97,11 +243,13 @@ impl From<UaCategory> for u8 {
             UaCategory::Open => 1,
             UaCategory::Specific => 2,
             UaCategory::Certified => 3,
+            UaCategory::Unknown(value) => value,
         }
     }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UaClass {
     Undefined,
     Class0,
@@ -111,6 +259,8 @@ pub enum UaClass {
     Class4,
     Class5,
     Class6,
+
+    Unknown(u8),
 }
 
 impl From<u8> for UaClass {
@@ -125,7 +275,8 @@ impl From<u8> for UaClass {
             6 => UaClass::Class5,
             7 => UaClass::Class6,
 
-            _ => UaClass::Undefined,
+            // 8-15: Reserved
+            _ => UaClass::Unknown(value),
         }
     }
 }
@@ -141,6 +292,7 @@ impl From<UaClass> for u8 {
             UaClass::Class4 => 5,
             UaClass::Class5 => 6,
             UaClass::Class6 => 7,
+            UaClass::Unknown(value) => value,
         }
     }
 }
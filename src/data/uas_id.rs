@@ -0,0 +1,423 @@
+use core::fmt;
+
+use super::basic_id::IdType;
+use crate::codec::copy_to_id;
+use crate::MAX_ID_BYTE_SIZE;
+
+/// Bytes of a UTM assigned UUID
+pub const UUID_BYTE_SIZE: usize = 16;
+
+/// Bytes of a specific session ID after the session ID type
+pub const SESSION_ID_BYTE_SIZE: usize = MAX_ID_BYTE_SIZE - 1;
+
+/// The UAS ID of a Basic ID message, interpreted according to its ID type
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UasId {
+    None,
+    /// ANSI/CTA-2063-A
+    SerialNumber(SerialNumber),
+    CaaRegistrationId(CaaRegistrationId),
+    UtmAssignedId(UtmAssignedId),
+    SpecificSessionId(SpecificSessionId),
+
+    /// IDs of reserved ID types and IDs that do not match their ID type, kept as received
+    Invalid {
+        id_type: IdType,
+        #[cfg_attr(feature = "serde", serde(with = "super::serde_id"))]
+        id: [u8; MAX_ID_BYTE_SIZE],
+    },
+}
+
+impl UasId {
+    /// Interpret the raw ID according to its ID type
+    pub fn parse(id_type: IdType, id: &[u8; MAX_ID_BYTE_SIZE]) -> Result<Self, UasIdError> {
+        match id_type {
+            IdType::None if id.iter().all(|&b| b == 0) => Ok(UasId::None),
+            IdType::None => Err(UasIdError::InvalidPadding),
+            IdType::SerialNumber => SerialNumber::from_bytes(id).map(UasId::SerialNumber),
+            IdType::CaaRegistrationId => {
+                CaaRegistrationId::from_bytes(id).map(UasId::CaaRegistrationId)
+            }
+            IdType::UtmAssignedId => UtmAssignedId::from_bytes(id).map(UasId::UtmAssignedId),
+            IdType::SpecificSessionId => Ok(UasId::SpecificSessionId(SpecificSessionId {
+                session_id_type: id[0],
+                id: id[1..].try_into().expect("20 byte ID"),
+            })),
+            IdType::Unknown(_) => Err(UasIdError::ReservedIdType),
+        }
+    }
+
+    /// Like [`UasId::parse`], but keeps malformed IDs as [`UasId::Invalid`]
+    pub fn from_bytes(id_type: IdType, id: &[u8; MAX_ID_BYTE_SIZE]) -> Self {
+        Self::parse(id_type, id).unwrap_or(UasId::Invalid { id_type, id: *id })
+    }
+
+    pub fn id_type(&self) -> IdType {
+        match self {
+            UasId::None => IdType::None,
+            UasId::SerialNumber(_) => IdType::SerialNumber,
+            UasId::CaaRegistrationId(_) => IdType::CaaRegistrationId,
+            UasId::UtmAssignedId(_) => IdType::UtmAssignedId,
+            UasId::SpecificSessionId(_) => IdType::SpecificSessionId,
+            UasId::Invalid { id_type, .. } => *id_type,
+        }
+    }
+
+    /// The ID as transmitted, padded with NUL
+    pub fn to_bytes(&self) -> [u8; MAX_ID_BYTE_SIZE] {
+        match self {
+            UasId::None => [0; MAX_ID_BYTE_SIZE],
+            UasId::SerialNumber(serial_number) => serial_number.0,
+            UasId::CaaRegistrationId(registration_id) => registration_id.0,
+            UasId::UtmAssignedId(uuid) => copy_to_id(&uuid.0),
+            UasId::SpecificSessionId(session_id) => {
+                let mut id = [0; MAX_ID_BYTE_SIZE];
+                id[0] = session_id.session_id_type;
+                id[1..].copy_from_slice(&session_id.id);
+                id
+            }
+            UasId::Invalid { id, .. } => *id,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UasIdError {
+    /// The ID type is reserved by the specification
+    ReservedIdType,
+    /// The manufacturer code is not 4 digits or upper case letters other than `O` and `I`
+    InvalidManufacturerCode,
+    /// The length character is not one of `1`-`9` and `A`-`F`
+    InvalidLengthCharacter,
+    /// The serial does not have the length given by the length character
+    LengthMismatch,
+    /// The ID contains a character that is not allowed for its ID type
+    InvalidCharacter,
+    /// The ID does not fit into 20 bytes
+    TooLong,
+    /// The ID is followed by bytes other than NUL
+    InvalidPadding,
+}
+
+impl fmt::Display for UasIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UasIdError::ReservedIdType => write!(f, "reserved ID type"),
+            UasIdError::InvalidManufacturerCode => write!(f, "invalid manufacturer code"),
+            UasIdError::InvalidLengthCharacter => write!(f, "invalid length character"),
+            UasIdError::LengthMismatch => write!(f, "serial does not match its length character"),
+            UasIdError::InvalidCharacter => write!(f, "invalid character"),
+            UasIdError::TooLong => write!(f, "ID longer than {MAX_ID_BYTE_SIZE} bytes"),
+            UasIdError::InvalidPadding => write!(f, "ID not padded with NUL"),
+        }
+    }
+}
+
+impl core::error::Error for UasIdError {}
+
+/// Split the ID at the first NUL, all following bytes must be NUL as well
+fn trim_padding(id: &[u8]) -> Result<&[u8], UasIdError> {
+    let len = id.iter().position(|&b| b == 0).unwrap_or(id.len());
+    if id[len..].iter().any(|&b| b != 0) {
+        return Err(UasIdError::InvalidPadding);
+    }
+    Ok(&id[..len])
+}
+
+/// Serial number according to ANSI/CTA-2063-A
+///
+/// Consists of a 4 character manufacturer code assigned by the ICAO, a length character
+/// and the manufacturer serial number of up to 15 characters.
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SerialNumber(
+    #[cfg_attr(feature = "serde", serde(with = "super::serde_id"))] [u8; MAX_ID_BYTE_SIZE],
+);
+
+impl SerialNumber {
+    pub const MANUFACTURER_CODE_LEN: usize = 4;
+    pub const MAX_SERIAL_LEN: usize = 15;
+
+    pub fn new(manufacturer_code: &str, serial: &str) -> Result<Self, UasIdError> {
+        if manufacturer_code.len() != Self::MANUFACTURER_CODE_LEN
+            || !manufacturer_code.bytes().all(Self::is_valid_char)
+        {
+            return Err(UasIdError::InvalidManufacturerCode);
+        }
+        if serial.len() > Self::MAX_SERIAL_LEN {
+            return Err(UasIdError::TooLong);
+        }
+        if serial.is_empty() {
+            return Err(UasIdError::LengthMismatch);
+        }
+        if !serial.bytes().all(Self::is_valid_char) {
+            return Err(UasIdError::InvalidCharacter);
+        }
+
+        let mut id = [0; MAX_ID_BYTE_SIZE];
+        id[..4].copy_from_slice(manufacturer_code.as_bytes());
+        id[4] = b"0123456789ABCDEF"[serial.len()];
+        id[5..5 + serial.len()].copy_from_slice(serial.as_bytes());
+        Ok(Self(id))
+    }
+
+    /// Parse a complete serial number, e.g. `1596F359746167260749`
+    pub fn parse(serial_number: &str) -> Result<Self, UasIdError> {
+        if serial_number.len() > MAX_ID_BYTE_SIZE {
+            return Err(UasIdError::TooLong);
+        }
+        Self::from_bytes(&copy_to_id(serial_number.as_bytes()))
+    }
+
+    fn from_bytes(id: &[u8; MAX_ID_BYTE_SIZE]) -> Result<Self, UasIdError> {
+        let serial_number = trim_padding(id)?;
+        if serial_number.len() < 5 {
+            return Err(UasIdError::InvalidManufacturerCode);
+        }
+        if !serial_number[..4].iter().copied().all(Self::is_valid_char) {
+            return Err(UasIdError::InvalidManufacturerCode);
+        }
+        let len = match serial_number[4] {
+            c @ b'1'..=b'9' => c - b'0',
+            c @ b'A'..=b'F' => c - b'A' + 10,
+            _ => return Err(UasIdError::InvalidLengthCharacter),
+        };
+        let serial = &serial_number[5..];
+        if serial.len() != len as usize {
+            return Err(UasIdError::LengthMismatch);
+        }
+        if !serial.iter().copied().all(Self::is_valid_char) {
+            return Err(UasIdError::InvalidCharacter);
+        }
+        Ok(Self(*id))
+    }
+
+    /// Digits and upper case letters, except `O` and `I`
+    fn is_valid_char(c: u8) -> bool {
+        matches!(c, b'0'..=b'9' | b'A'..=b'Z') && c != b'O' && c != b'I'
+    }
+
+    pub fn as_str(&self) -> &str {
+        let len = self
+            .0
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(MAX_ID_BYTE_SIZE);
+        core::str::from_utf8(&self.0[..len]).expect("validated on construction")
+    }
+
+    pub fn manufacturer_code(&self) -> &str {
+        &self.as_str()[..4]
+    }
+
+    pub fn serial(&self) -> &str {
+        &self.as_str()[5..]
+    }
+}
+
+impl fmt::Debug for SerialNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SerialNumber").field(&self.as_str()).finish()
+    }
+}
+
+/// Validated like received IDs, as the accessors rely on a well formed ID
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SerialNumber {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = super::serde_id::deserialize(deserializer)?;
+        Self::from_bytes(&id).map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for SerialNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Registration ID assigned by a civil aviation authority, printable ASCII
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CaaRegistrationId(
+    #[cfg_attr(feature = "serde", serde(with = "super::serde_id"))] [u8; MAX_ID_BYTE_SIZE],
+);
+
+impl CaaRegistrationId {
+    pub fn new(registration_id: &str) -> Result<Self, UasIdError> {
+        if registration_id.len() > MAX_ID_BYTE_SIZE {
+            return Err(UasIdError::TooLong);
+        }
+        Self::from_bytes(&copy_to_id(registration_id.as_bytes()))
+    }
+
+    fn from_bytes(id: &[u8; MAX_ID_BYTE_SIZE]) -> Result<Self, UasIdError> {
+        let registration_id = trim_padding(id)?;
+        if registration_id.is_empty() || !registration_id.iter().all(u8::is_ascii_graphic) {
+            return Err(UasIdError::InvalidCharacter);
+        }
+        Ok(Self(*id))
+    }
+
+    pub fn as_str(&self) -> &str {
+        let len = self
+            .0
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(MAX_ID_BYTE_SIZE);
+        core::str::from_utf8(&self.0[..len]).expect("validated on construction")
+    }
+}
+
+impl fmt::Debug for CaaRegistrationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CaaRegistrationId")
+            .field(&self.as_str())
+            .finish()
+    }
+}
+
+/// Validated like received IDs, as the accessors rely on a well formed ID
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CaaRegistrationId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = super::serde_id::deserialize(deserializer)?;
+        Self::from_bytes(&id).map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for CaaRegistrationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// UUID assigned by a UTM service provider, followed by 4 NUL bytes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UtmAssignedId(pub [u8; UUID_BYTE_SIZE]);
+
+impl UtmAssignedId {
+    fn from_bytes(id: &[u8; MAX_ID_BYTE_SIZE]) -> Result<Self, UasIdError> {
+        if id[UUID_BYTE_SIZE..].iter().any(|&b| b != 0) {
+            return Err(UasIdError::InvalidPadding);
+        }
+        Ok(Self(id[..UUID_BYTE_SIZE].try_into().expect("16 byte UUID")))
+    }
+}
+
+impl fmt::Display for UtmAssignedId {
+    /// Hyphenated lower case form, e.g. `67e55044-10b1-426f-9247-bb680e5fe0c8`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if matches!(i, 4 | 6 | 8 | 10) {
+                f.write_str("-")?;
+            }
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Session ID, the first byte gives the type of the session ID
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpecificSessionId {
+    /// 0: Reserved, 1: IETF DRIP, 2: IEEE 1609.2-2016 HashedID8, 3-223: Reserved,
+    /// 224-255: Available for private use
+    pub session_id_type: u8,
+    pub id: [u8; SESSION_ID_BYTE_SIZE],
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn parse_serial_number() {
+        let serial_number = SerialNumber::parse("1596F359746167260749").unwrap();
+
+        assert_eq!("1596", serial_number.manufacturer_code());
+        assert_eq!("359746167260749", serial_number.serial());
+        assert_eq!(
+            serial_number,
+            SerialNumber::new("1596", "359746167260749").unwrap()
+        );
+
+        let serial_number = SerialNumber::new("MFR1", "A12").unwrap();
+        assert_eq!("MFR13A12", serial_number.as_str());
+    }
+
+    #[test]
+    fn reject_malformed_serial_numbers() {
+        assert_eq!(
+            Err(UasIdError::InvalidManufacturerCode),
+            SerialNumber::parse("15O6F359746167260749")
+        );
+        assert_eq!(
+            Err(UasIdError::InvalidLengthCharacter),
+            SerialNumber::parse("15960359746167260749")
+        );
+        assert_eq!(
+            Err(UasIdError::LengthMismatch),
+            SerialNumber::parse("1596F35974616726074")
+        );
+        assert_eq!(
+            Err(UasIdError::InvalidCharacter),
+            SerialNumber::parse("15963abc")
+        );
+    }
+
+    #[test]
+    fn malformed_ids_are_kept() {
+        let id = copy_to_id(b"1596F35974616726074");
+        let uas_id = UasId::from_bytes(IdType::SerialNumber, &id);
+
+        assert_eq!(
+            UasId::Invalid {
+                id_type: IdType::SerialNumber,
+                id
+            },
+            uas_id
+        );
+        assert_eq!(
+            Err(UasIdError::LengthMismatch),
+            UasId::parse(IdType::SerialNumber, &id)
+        );
+        assert_eq!(id, uas_id.to_bytes());
+    }
+
+    #[test]
+    fn uas_id_bytes_roundtrip() {
+        let mut session_id = [7; MAX_ID_BYTE_SIZE];
+        session_id[0] = 1;
+        let mut uuid = [0; MAX_ID_BYTE_SIZE];
+        uuid[..UUID_BYTE_SIZE].copy_from_slice(&[0xAB; UUID_BYTE_SIZE]);
+
+        for (id_type, id) in [
+            (IdType::None, [0; MAX_ID_BYTE_SIZE]),
+            (IdType::SerialNumber, copy_to_id(b"1596F359746167260749")),
+            (IdType::CaaRegistrationId, copy_to_id(b"FIN87astrdge12k8")),
+            (IdType::UtmAssignedId, uuid),
+            (IdType::SpecificSessionId, session_id),
+        ] {
+            let uas_id = UasId::parse(id_type, &id).unwrap();
+            assert_eq!(id_type, uas_id.id_type());
+            assert_eq!(id, uas_id.to_bytes());
+        }
+    }
+
+    #[test]
+    fn display_uuid() {
+        let uuid = UtmAssignedId([
+            0x67, 0xe5, 0x50, 0x44, 0x10, 0xb1, 0x42, 0x6f, 0x92, 0x47, 0xbb, 0x68, 0x0e, 0x5f,
+            0xe0, 0xc8,
+        ]);
+        assert_eq!("67e55044-10b1-426f-9247-bb680e5fe0c8", uuid.to_string());
+    }
+}
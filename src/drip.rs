@@ -0,0 +1,305 @@
+//! DRIP Entity Tags (DET) as Specific Session ID (IETF RFC 9374, RFC 9575)
+//!
+//! A DET is a Hierarchical Host Identity Tag (HHIT), an IPv6 address made of
+//!
+//! | Prefix  | HID (RAA, HDA)   | Suite ID | Hash    |
+//! |---------|------------------|----------|---------|
+//! | 28 bits | 28 bits (14, 14) | 8 bits   | 64 bits |
+//!
+//! where the hash binds the tag to the Ed25519 public key of the aircraft.
+//! It is broadcast in the Basic ID message as Specific Session ID of type IETF DRIP.
+
+use core::fmt;
+use core::net::Ipv6Addr;
+
+use ed25519_dalek::VerifyingKey;
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use sha3::{CShake128, CShake128Core};
+
+use crate::data::uas_id::{SpecificSessionId, UasId, SESSION_ID_BYTE_SIZE};
+
+/// Session ID type of the IETF DRIP Entity Tag
+pub const DRIP_SESSION_ID_TYPE: u8 = 1;
+
+/// Upper 28 bits of the DET prefix 2001:30::/28
+pub const DET_PREFIX: u32 = 0x0200_1003;
+
+/// Suite ID (ORCHID Generation Algorithm) for EdDSA/cSHAKE128
+pub const SUITE_ID_EDDSA_CSHAKE128: u8 = 5;
+
+pub const DET_BYTE_SIZE: usize = 16;
+
+/// Largest value of the 14 bit RAA and HDA fields
+pub const MAX_HID_FIELD: u16 = 0x3FFF;
+
+/// ORCHID context ID of HHITs
+const CONTEXT_ID: [u8; 16] = [
+    0x00, 0xB5, 0xA6, 0x9C, 0x79, 0x5D, 0xF5, 0xD5, 0xF0, 0x08, 0x7F, 0x56, 0x84, 0x3F, 0x2C, 0x40,
+];
+
+/// EdDSA curve label of Ed25519, prepended to the public key to form the Host Identity
+const EDDSA_CURVE_ED25519: [u8; 2] = [0x00, 0x01];
+
+/// Hierarchy ID, the registry the DET is registered with
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Hid {
+    /// Registered Assigning Authority
+    pub raa: u16,
+    /// HHIT Domain Authority
+    pub hda: u16,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DripError {
+    /// RAA or HDA does not fit into 14 bits
+    HidOutOfRange,
+    /// The tag does not start with the DET prefix
+    InvalidPrefix,
+    /// Only EdDSA/cSHAKE128 is supported
+    UnsupportedSuite(u8),
+    /// The UAS ID is no Specific Session ID of type IETF DRIP
+    NotDripSessionId,
+    /// The bytes following the DET are not zero
+    InvalidPadding,
+}
+
+impl fmt::Display for DripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DripError::HidOutOfRange => write!(f, "RAA or HDA out of range"),
+            DripError::InvalidPrefix => write!(f, "invalid DET prefix"),
+            DripError::UnsupportedSuite(suite) => write!(f, "unsupported suite ID: {suite}"),
+            DripError::NotDripSessionId => write!(f, "not a DRIP session ID"),
+            DripError::InvalidPadding => write!(f, "invalid padding after DET"),
+        }
+    }
+}
+
+impl core::error::Error for DripError {}
+
+/// DRIP Entity Tag
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Det([u8; DET_BYTE_SIZE]);
+
+impl Det {
+    /// Generate the DET of an Ed25519 public key registered under `hid`
+    pub fn new(hid: Hid, public_key: &VerifyingKey) -> Result<Self, DripError> {
+        if hid.raa > MAX_HID_FIELD || hid.hda > MAX_HID_FIELD {
+            return Err(DripError::HidOutOfRange);
+        }
+
+        // prefix (28) | raa (14) | hda (14) | suite (8)
+        let head = (DET_PREFIX as u64) << 36
+            | (hid.raa as u64) << 22
+            | (hid.hda as u64) << 8
+            | SUITE_ID_EDDSA_CSHAKE128 as u64;
+
+        let head = head.to_be_bytes();
+        let mut det = [0; DET_BYTE_SIZE];
+        det[..8].copy_from_slice(&head);
+        det[8..].copy_from_slice(&hash(&head, public_key));
+        Ok(Self(det))
+    }
+
+    /// Interpret received bytes as DET, the hash can only be checked with [`Det::verify`]
+    pub fn from_bytes(det: [u8; DET_BYTE_SIZE]) -> Result<Self, DripError> {
+        let det = Self(det);
+        if det.head() >> 36 != DET_PREFIX as u64 {
+            return Err(DripError::InvalidPrefix);
+        }
+        if det.suite_id() != SUITE_ID_EDDSA_CSHAKE128 {
+            return Err(DripError::UnsupportedSuite(det.suite_id()));
+        }
+        Ok(det)
+    }
+
+    /// Extract the DET from a Specific Session ID of type IETF DRIP
+    pub fn from_uas_id(uas_id: &UasId) -> Result<Self, DripError> {
+        let UasId::SpecificSessionId(SpecificSessionId {
+            session_id_type: DRIP_SESSION_ID_TYPE,
+            id,
+        }) = uas_id
+        else {
+            return Err(DripError::NotDripSessionId);
+        };
+
+        if id[DET_BYTE_SIZE..].iter().any(|&b| b != 0) {
+            return Err(DripError::InvalidPadding);
+        }
+        Self::from_bytes(id[..DET_BYTE_SIZE].try_into().unwrap())
+    }
+
+    /// The DET as Specific Session ID, to be used as [`BasicId::uas_id`](crate::data::basic_id::BasicId::uas_id)
+    pub fn to_uas_id(&self) -> UasId {
+        let mut id = [0; SESSION_ID_BYTE_SIZE];
+        id[..DET_BYTE_SIZE].copy_from_slice(&self.0);
+        UasId::SpecificSessionId(SpecificSessionId {
+            session_id_type: DRIP_SESSION_ID_TYPE,
+            id,
+        })
+    }
+
+    /// Check that the DET was generated from `public_key`
+    pub fn verify(&self, public_key: &VerifyingKey) -> bool {
+        self.0[8..] == hash(&self.0[..8], public_key)
+    }
+
+    pub fn hid(&self) -> Hid {
+        Hid {
+            raa: self.raa(),
+            hda: self.hda(),
+        }
+    }
+
+    pub fn raa(&self) -> u16 {
+        (self.head() >> 22) as u16 & MAX_HID_FIELD
+    }
+
+    pub fn hda(&self) -> u16 {
+        (self.head() >> 8) as u16 & MAX_HID_FIELD
+    }
+
+    pub fn suite_id(&self) -> u8 {
+        self.0[7]
+    }
+
+    pub fn to_bytes(&self) -> [u8; DET_BYTE_SIZE] {
+        self.0
+    }
+
+    fn head(&self) -> u64 {
+        u64::from_be_bytes(self.0[..8].try_into().unwrap())
+    }
+}
+
+impl From<Det> for Ipv6Addr {
+    fn from(det: Det) -> Self {
+        Ipv6Addr::from(det.0)
+    }
+}
+
+impl fmt::Debug for Det {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Det").field(&Ipv6Addr::from(*self)).finish()
+    }
+}
+
+/// Written in IPv6 notation
+impl fmt::Display for Det {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Ipv6Addr::from(*self).fmt(f)
+    }
+}
+
+/// cSHAKE128(prefix | HID | suite ID | HI, 64, "", context ID), with HI = curve label | public key
+fn hash(head: &[u8], public_key: &VerifyingKey) -> [u8; 8] {
+    let mut hasher = CShake128::from_core(CShake128Core::new_with_function_name(b"", &CONTEXT_ID));
+    hasher.update(head);
+    hasher.update(&EDDSA_CURVE_ED25519);
+    hasher.update(public_key.as_bytes());
+
+    let mut hash = [0; 8];
+    hasher.finalize_xof().read(&mut hash);
+    hash
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+
+    use std::string::ToString;
+
+    use ed25519_dalek::SigningKey;
+
+    use super::*;
+    use crate::codec::decode::from_service_data;
+    use crate::codec::encode::to_service_data;
+    use crate::data::basic_id::{BasicId, UAType};
+    use crate::data::RemoteIDMessage;
+
+    fn public_key(seed: u8) -> VerifyingKey {
+        SigningKey::from_bytes(&[seed; 32]).verifying_key()
+    }
+
+    #[test]
+    fn det_from_public_key() {
+        let hid = Hid {
+            raa: 16376,
+            hda: 20,
+        };
+        let det = Det::new(hid, &public_key(1)).unwrap();
+
+        assert_eq!(hid, det.hid());
+        assert_eq!(SUITE_ID_EDDSA_CSHAKE128, det.suite_id());
+        assert!(det.to_string().starts_with("2001:3f:fe00:1405:"));
+        assert!(det.verify(&public_key(1)));
+        assert!(!det.verify(&public_key(2)));
+        assert_ne!(det, Det::new(hid, &public_key(2)).unwrap());
+    }
+
+    #[test]
+    fn hash_covers_host_identity() {
+        let det = Det::new(Hid { raa: 1, hda: 2 }, &public_key(1)).unwrap();
+
+        // ORCHID input of RFC 9374 section 3.4 with the HI as EdDSA curve label and public key
+        let mut input = std::vec::Vec::from(&det.to_bytes()[..8]);
+        input.extend_from_slice(&[0x00, 0x01]);
+        input.extend_from_slice(public_key(1).as_bytes());
+
+        let mut hasher =
+            CShake128::from_core(CShake128Core::new_with_function_name(b"", &CONTEXT_ID));
+        hasher.update(&input);
+        let mut hash = [0; 8];
+        hasher.finalize_xof().read(&mut hash);
+        assert_eq!(hash, det.to_bytes()[8..]);
+    }
+
+    #[test]
+    fn det_in_basic_id() {
+        let det = Det::new(Hid { raa: 1, hda: 2 }, &public_key(1)).unwrap();
+        let basic_id = RemoteIDMessage::BasicID(
+            BasicId::builder()
+                .ua_type(UAType::HelicopterOrMultirotor)
+                .uas_id(det.to_uas_id())
+                .build()
+                .unwrap(),
+        );
+
+        let service_data = to_service_data(&basic_id, 0);
+        let RemoteIDMessage::BasicID(received) = from_service_data(&service_data).unwrap() else {
+            panic!("expected basic id");
+        };
+
+        let received = Det::from_uas_id(&received.uas_id).unwrap();
+        assert_eq!(det, received);
+        assert_eq!(1, received.raa());
+        assert_eq!(2, received.hda());
+    }
+
+    #[test]
+    fn reject_invalid_det() {
+        assert_eq!(
+            Err(DripError::HidOutOfRange),
+            Det::new(
+                Hid {
+                    raa: 0x4000,
+                    hda: 0
+                },
+                &public_key(1)
+            )
+        );
+
+        let mut bytes = Det::new(Hid { raa: 1, hda: 2 }, &public_key(1))
+            .unwrap()
+            .to_bytes();
+        bytes[7] = 1;
+        assert_eq!(Err(DripError::UnsupportedSuite(1)), Det::from_bytes(bytes));
+        bytes[0] = 0xfe;
+        assert_eq!(Err(DripError::InvalidPrefix), Det::from_bytes(bytes));
+
+        assert_eq!(
+            Err(DripError::NotDripSessionId),
+            Det::from_uas_id(&UasId::None)
+        );
+    }
+}
@@ -1,10 +1,23 @@
 #![no_std]
 
+extern crate alloc;
+
+#[cfg(feature = "auth")]
+pub mod auth;
+pub mod capture;
 pub mod codec;
 pub mod data;
+#[cfg(feature = "drip")]
+pub mod drip;
+pub mod stats;
+pub mod tracker;
+pub mod transport;
 
 const MAX_ID_BYTE_SIZE: usize = 20;
 
+/// Remote ID timestamps count seconds since 2019-01-01 00:00:00 UTC
+const REMOTE_ID_EPOCH: i64 = 1546300800;
+
 // https://github.com/opendroneid/receiver-android/blob/a6359b6ee7c2b06c035137c8348cf979705624c3/Android/app/src/main/java/org/opendroneid/android/bluetooth/BluetoothScanner.java#L121
 const OPEN_DRONE_ID_AD_CODE: u8 = 0x0D;
 
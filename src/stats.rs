@@ -0,0 +1,198 @@
+//! Reception quality from the message counters of received service data
+//!
+//! Each transmitter keeps an 8 bit counter per message type, incremented with every new
+//! message. A gap in the counter means messages were lost, a repeated counter that the
+//! message was received more than once. A counter jumping backwards means the transmitter
+//! restarted its counter, e.g. after a reboot.
+
+use alloc::collections::BTreeMap;
+
+use crate::codec::{MessageType, ServiceDataFrame};
+
+/// Largest counter increment still counted as lost messages, larger increments
+/// are counters running backwards and counted as resets
+pub const MAX_COUNTER_GAP: u8 = 127;
+
+/// Counter statistics of a single message type of a single sender
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct CounterStats {
+    /// All received frames, including duplicates
+    pub received: u64,
+    /// Frames with the same counter as the previous frame
+    pub duplicates: u64,
+    /// Frames missing between two received counters
+    pub lost: u64,
+    /// Times the counter jumped backwards
+    pub resets: u64,
+    pub last_counter: Option<u8>,
+}
+
+impl CounterStats {
+    /// Account for a received counter
+    pub fn record(&mut self, counter: u8) {
+        self.received += 1;
+
+        let Some(last_counter) = self.last_counter.replace(counter) else {
+            return;
+        };
+        match counter.wrapping_sub(last_counter) {
+            0 => self.duplicates += 1,
+            gap @ 1..=MAX_COUNTER_GAP => self.lost += gap as u64 - 1,
+            _ => self.resets += 1,
+        }
+    }
+
+    /// Share of the sent frames that were not received, duplicates not counted
+    pub fn loss_rate(&self) -> f32 {
+        let unique = self.received - self.duplicates;
+        if unique + self.lost == 0 {
+            return 0.;
+        }
+        self.lost as f32 / (unique + self.lost) as f32
+    }
+}
+
+/// Counter statistics per sender and message type
+#[derive(Debug, Clone)]
+pub struct ReceptionStats<A> {
+    stats: BTreeMap<(A, MessageType), CounterStats>,
+}
+
+impl<A> Default for ReceptionStats<A> {
+    fn default() -> Self {
+        Self {
+            stats: BTreeMap::new(),
+        }
+    }
+}
+
+impl<A: Ord + Clone> ReceptionStats<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Account for a frame received from `address`, returns the updated statistics
+    pub fn record(&mut self, address: A, frame: &ServiceDataFrame) -> &CounterStats {
+        let stats = self
+            .stats
+            .entry((address, frame.message_type()))
+            .or_default();
+        stats.record(frame.counter);
+        stats
+    }
+
+    pub fn get(&self, address: &A, message_type: MessageType) -> Option<&CounterStats> {
+        self.stats.get(&(address.clone(), message_type))
+    }
+
+    /// Statistics of all message types of a sender
+    pub fn sender<'a>(
+        &'a self,
+        address: &'a A,
+    ) -> impl Iterator<Item = (MessageType, &'a CounterStats)> + 'a {
+        self.stats
+            .iter()
+            .filter(move |((a, _), _)| a == address)
+            .map(|((_, message_type), stats)| (*message_type, stats))
+    }
+
+    /// Statistics summed up over all message types of a sender
+    pub fn total(&self, address: &A) -> CounterStats {
+        self.sender(address)
+            .fold(CounterStats::default(), |mut total, (_, stats)| {
+                total.received += stats.received;
+                total.duplicates += stats.duplicates;
+                total.lost += stats.lost;
+                total.resets += stats.resets;
+                total
+            })
+    }
+
+    /// Forget a sender, e.g. once its aircraft expired
+    pub fn remove(&mut self, address: &A) {
+        self.stats.retain(|(a, _), _| a != address);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&A, MessageType, &CounterStats)> {
+        self.stats
+            .iter()
+            .map(|((address, message_type), stats)| (address, *message_type, stats))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codec::decode::from_service_data_frame;
+    use crate::codec::encode::to_service_data;
+    use crate::data::location::Location;
+    use crate::data::operator_id::OperatorId;
+    use crate::data::RemoteIDMessage;
+
+    fn frame(counter: u8) -> ServiceDataFrame {
+        let location = Location::builder().build().unwrap();
+        from_service_data_frame(&to_service_data(
+            &RemoteIDMessage::Location(location),
+            counter,
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn count_lost_and_duplicate_frames() {
+        let mut stats = CounterStats::default();
+        for counter in [254, 255, 255, 2, 3] {
+            stats.record(counter);
+        }
+
+        assert_eq!(5, stats.received);
+        assert_eq!(1, stats.duplicates);
+        assert_eq!(2, stats.lost);
+        assert_eq!(0, stats.resets);
+        assert_eq!(Some(3), stats.last_counter);
+        assert_eq!(2. / 6., stats.loss_rate());
+    }
+
+    #[test]
+    fn count_counter_resets() {
+        let mut stats = CounterStats::default();
+        for counter in [40, 41, 0, 1] {
+            stats.record(counter);
+        }
+
+        assert_eq!(1, stats.resets);
+        assert_eq!(0, stats.lost);
+        assert_eq!(0., stats.loss_rate());
+    }
+
+    #[test]
+    fn separate_senders_and_message_types() {
+        let mut stats = ReceptionStats::new();
+        let operator_id = OperatorId::builder().build().unwrap();
+        let operator_id = from_service_data_frame(&to_service_data(
+            &RemoteIDMessage::OperatorId(operator_id),
+            10,
+        ))
+        .unwrap();
+        assert_eq!(10, operator_id.counter);
+
+        stats.record("a", &frame(1));
+        stats.record("a", &operator_id);
+        stats.record("b", &frame(5));
+        assert_eq!(1, stats.record("a", &frame(3)).lost);
+
+        assert_eq!(
+            Some(10),
+            stats
+                .get(&"a", MessageType::OperatorId)
+                .unwrap()
+                .last_counter
+        );
+        assert_eq!(2, stats.sender(&"a").count());
+        assert_eq!(3, stats.total(&"a").received);
+        assert_eq!(0, stats.total(&"b").lost);
+
+        stats.remove(&"a");
+        assert_eq!(1, stats.iter().count());
+    }
+}
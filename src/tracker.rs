@@ -0,0 +1,620 @@
+//! Merge the separately received messages of an aircraft into a single state
+//!
+//! The tracker is fed with every received message and its sender address, e.g. the BLE MAC
+//! address, and does not depend on the source of the messages. Receive times are passed in,
+//! so recorded messages can be replayed offline.
+//!
+//! Transmitters may change their address while in flight, e.g. due to BLE MAC address
+//! randomization. A new address first starts a new track, which is merged into the known
+//! track of the aircraft as soon as both share a UAS ID or, if only one of them received a
+//! UAS ID yet, an operator ID.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::mem;
+use core::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::data::authentication::{AuthenticationData, AuthenticationReassembler};
+use crate::data::basic_id::{BasicId, IdType};
+use crate::data::location::Location;
+use crate::data::operator_id::OperatorId;
+use crate::data::self_id::SelfId;
+use crate::data::system::System;
+use crate::data::uas_id::UasId;
+use crate::data::RemoteIDMessage;
+
+/// A message and the time it was received
+#[derive(Debug, Clone, PartialEq)]
+pub struct Received<T> {
+    pub message: T,
+    pub received: DateTime<Utc>,
+}
+
+/// Identifies an aircraft independent of its sender address
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TrackId(u64);
+
+/// Latest messages of every message type received from an aircraft
+#[derive(Debug, Clone)]
+pub struct UasState<A> {
+    pub track_id: TrackId,
+    /// Address the last message was received from
+    pub address: A,
+    /// All addresses the aircraft used, in the order they were first seen
+    pub addresses: Vec<A>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// One per ID type, aircraft may broadcast e.g. their serial number and registration ID
+    pub basic_ids: Vec<Received<BasicId>>,
+    pub location: Option<Received<Location>>,
+    /// Last completely reassembled authentication
+    pub authentication: Option<Received<AuthenticationData>>,
+    pub self_id: Option<Received<SelfId>>,
+    pub system: Option<Received<System>>,
+    pub operator_id: Option<Received<OperatorId>>,
+    reassembler: AuthenticationReassembler,
+}
+
+impl<A: PartialEq + Clone> UasState<A> {
+    fn new(track_id: TrackId, address: A, received: DateTime<Utc>) -> Self {
+        Self {
+            track_id,
+            addresses: alloc::vec![address.clone()],
+            address,
+            first_seen: received,
+            last_seen: received,
+            basic_ids: Vec::new(),
+            location: None,
+            authentication: None,
+            self_id: None,
+            system: None,
+            operator_id: None,
+            reassembler: AuthenticationReassembler::new(),
+        }
+    }
+
+    /// Basic ID of the given ID type
+    pub fn basic_id(&self, id_type: IdType) -> Option<&BasicId> {
+        self.basic_ids
+            .iter()
+            .map(|basic_id| &basic_id.message)
+            .find(|basic_id| basic_id.id_type() == id_type)
+    }
+
+    /// How both states identify the same aircraft
+    ///
+    /// A shared UAS ID identifies the aircraft. Aircraft of the same operator share the
+    /// operator ID, so it only associates a track without UAS ID with an identified one.
+    fn association(&self, other: &Self) -> Option<Association> {
+        let uas_ids = || {
+            self.basic_ids
+                .iter()
+                .map(|b| &b.message.uas_id)
+                .filter(|uas_id| !matches!(uas_id, UasId::None))
+        };
+        let other_uas_id = |id_type| {
+            other
+                .basic_id(id_type)
+                .map(|b| &b.uas_id)
+                .filter(|uas_id| !matches!(uas_id, UasId::None))
+        };
+
+        let mut contradicting = false;
+        for uas_id in uas_ids() {
+            match other_uas_id(uas_id.id_type()) {
+                Some(other) if other == uas_id => return Some(Association::UasId),
+                Some(_) => contradicting = true,
+                None => {}
+            }
+        }
+        let identified = uas_ids().next().is_some();
+        let other_identified = other
+            .basic_ids
+            .iter()
+            .any(|b| !matches!(b.message.uas_id, UasId::None));
+        if contradicting || identified == other_identified {
+            return None;
+        }
+
+        let operator_id = self.operator_id.as_ref()?.message.operator_id;
+        let same_operator = operator_id.iter().any(|&b| b != 0)
+            && operator_id == other.operator_id.as_ref()?.message.operator_id;
+        same_operator.then_some(Association::OperatorId)
+    }
+
+    fn update(&mut self, address: A, message: RemoteIDMessage, received: DateTime<Utc>) {
+        self.first_seen = self.first_seen.min(received);
+        if received >= self.last_seen {
+            self.last_seen = received;
+            self.address = address.clone();
+        }
+
+        match message {
+            RemoteIDMessage::BasicID(basic_id) => update_basic_id(
+                &mut self.basic_ids,
+                Received {
+                    message: basic_id,
+                    received,
+                },
+            ),
+            RemoteIDMessage::Location(location) => update(&mut self.location, location, received),
+            RemoteIDMessage::Authentication(page) => {
+                if let Some(auth) = self.reassembler.push(page) {
+                    update(&mut self.authentication, auth, received);
+                }
+            }
+            RemoteIDMessage::SelfId(self_id) => update(&mut self.self_id, self_id, received),
+            RemoteIDMessage::System(system) => update(&mut self.system, system, received),
+            RemoteIDMessage::OperatorId(operator_id) => {
+                update(&mut self.operator_id, operator_id, received)
+            }
+            RemoteIDMessage::MessagePack(messages) => {
+                for message in messages {
+                    self.update(address.clone(), message, received);
+                }
+            }
+        }
+    }
+
+    /// Take over the addresses and the newer messages of another track of the same aircraft
+    fn merge(&mut self, other: Self) {
+        self.first_seen = self.first_seen.min(other.first_seen);
+        // authentication pages may be split across both tracks, the newer ones take precedence
+        let (older, newer) = if other.last_seen >= self.last_seen {
+            self.last_seen = other.last_seen;
+            self.address = other.address;
+            (mem::take(&mut self.reassembler), other.reassembler)
+        } else {
+            (other.reassembler, mem::take(&mut self.reassembler))
+        };
+        for address in other.addresses {
+            if !self.addresses.contains(&address) {
+                self.addresses.push(address);
+            }
+        }
+
+        for basic_id in other.basic_ids {
+            update_basic_id(&mut self.basic_ids, basic_id);
+        }
+        merge(&mut self.location, other.location);
+        merge(&mut self.authentication, other.authentication);
+        merge(&mut self.self_id, other.self_id);
+        merge(&mut self.system, other.system);
+        merge(&mut self.operator_id, other.operator_id);
+
+        self.reassembler = older;
+        if let Some(auth) = self.reassembler.merge(newer) {
+            update(&mut self.authentication, auth, self.last_seen);
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Association {
+    UasId,
+    OperatorId,
+}
+
+/// Keep the newer message, messages may be replayed out of order
+fn replace_older<T>(previous: &mut Received<T>, latest: Received<T>) {
+    if latest.received >= previous.received {
+        *previous = latest;
+    }
+}
+
+fn update<T>(previous: &mut Option<Received<T>>, message: T, received: DateTime<Utc>) {
+    merge(previous, Some(Received { message, received }));
+}
+
+fn merge<T>(previous: &mut Option<Received<T>>, latest: Option<Received<T>>) {
+    match (previous.as_mut(), latest) {
+        (Some(previous), Some(latest)) => replace_older(previous, latest),
+        (None, latest) => *previous = latest,
+        (_, None) => {}
+    }
+}
+
+fn update_basic_id(basic_ids: &mut Vec<Received<BasicId>>, latest: Received<BasicId>) {
+    match basic_ids
+        .iter_mut()
+        .find(|b| b.message.id_type() == latest.message.id_type())
+    {
+        Some(previous) => replace_older(previous, latest),
+        None => basic_ids.push(latest),
+    }
+}
+
+/// Two tracks turned out to be the same aircraft
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MergeEvent {
+    /// The remaining track, the one seen first
+    pub track_id: TrackId,
+    /// The track merged into `track_id`, which no longer exists
+    pub merged: TrackId,
+}
+
+/// Result of [`UasTracker::ingest`]
+#[derive(Debug)]
+pub struct Ingested<'a, A> {
+    /// State of the aircraft the message belongs to
+    pub state: &'a UasState<A>,
+    /// Tracks merged due to the message
+    pub merges: Vec<MergeEvent>,
+}
+
+/// Tracks the state of all aircraft in reception range
+#[derive(Debug, Clone)]
+pub struct UasTracker<A> {
+    timeout: TimeDelta,
+    next_track_id: u64,
+    aircraft: BTreeMap<TrackId, UasState<A>>,
+    tracks: BTreeMap<A, TrackId>,
+}
+
+impl<A: Ord + Clone> UasTracker<A> {
+    /// Aircraft are considered gone if nothing was received for `timeout`
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout: TimeDelta::from_std(timeout).unwrap_or(TimeDelta::MAX),
+            next_track_id: 0,
+            aircraft: BTreeMap::new(),
+            tracks: BTreeMap::new(),
+        }
+    }
+
+    /// Merge a received message into the state of its aircraft
+    pub fn ingest(
+        &mut self,
+        address: A,
+        message: RemoteIDMessage,
+        received: DateTime<Utc>,
+    ) -> Ingested<'_, A> {
+        let mut track_id = match self.tracks.get(&address) {
+            Some(&track_id) => track_id,
+            None => {
+                let track_id = TrackId(self.next_track_id);
+                self.next_track_id += 1;
+                self.tracks.insert(address.clone(), track_id);
+                self.aircraft
+                    .insert(track_id, UasState::new(track_id, address.clone(), received));
+                track_id
+            }
+        };
+        self.aircraft
+            .get_mut(&track_id)
+            .expect("every address belongs to a track")
+            .update(address, message, received);
+
+        let mut merges = Vec::new();
+        while let Some(merge) = self.associate(track_id) {
+            track_id = merge.track_id;
+            merges.push(merge);
+        }
+
+        Ingested {
+            state: &self.aircraft[&track_id],
+            merges,
+        }
+    }
+
+    /// Merge the track with another track of the same aircraft, if any
+    ///
+    /// Tracks sharing only the operator ID are merged if there is no other track of the
+    /// operator, which could be the aircraft as well.
+    fn associate(&mut self, track_id: TrackId) -> Option<MergeEvent> {
+        let state = &self.aircraft[&track_id];
+        let candidates: Vec<_> = self
+            .aircraft
+            .values()
+            .filter(|other| other.track_id != track_id)
+            .filter_map(|other| Some((state.association(other)?, other)))
+            .collect();
+
+        let &(association, other) = candidates
+            .iter()
+            .min_by_key(|(association, other)| (*association, other.first_seen))?;
+        if association == Association::OperatorId && candidates.len() > 1 {
+            return None;
+        }
+
+        // keep the track that was seen first
+        let (remaining, merged) =
+            if (other.first_seen, other.track_id) < (state.first_seen, state.track_id) {
+                (other.track_id, track_id)
+            } else {
+                (track_id, other.track_id)
+            };
+        let merged = self.aircraft.remove(&merged)?;
+        for address in &merged.addresses {
+            self.tracks.insert(address.clone(), remaining);
+        }
+
+        let event = MergeEvent {
+            track_id: remaining,
+            merged: merged.track_id,
+        };
+        self.aircraft.get_mut(&remaining)?.merge(merged);
+        Some(event)
+    }
+
+    /// Remove and return all aircraft not heard of within the timeout before `now`
+    ///
+    /// Call periodically with the current time, or with the receive time of the last
+    /// message when replaying.
+    pub fn expire(&mut self, now: DateTime<Utc>) -> Vec<UasState<A>> {
+        let expired: Vec<TrackId> = self
+            .aircraft
+            .iter()
+            .filter(|(_, state)| now.signed_duration_since(state.last_seen) > self.timeout)
+            .map(|(track_id, _)| *track_id)
+            .collect();
+        self.tracks
+            .retain(|_, track_id| !expired.contains(track_id));
+        expired
+            .iter()
+            .filter_map(|track_id| self.aircraft.remove(track_id))
+            .collect()
+    }
+
+    /// State of the aircraft that used the address
+    pub fn get(&self, address: &A) -> Option<&UasState<A>> {
+        self.aircraft.get(self.tracks.get(address)?)
+    }
+
+    pub fn get_track(&self, track_id: TrackId) -> Option<&UasState<A>> {
+        self.aircraft.get(&track_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &UasState<A>> {
+        self.aircraft.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.aircraft.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aircraft.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::codec::ProtocolVersion;
+    use crate::data::authentication::Authentication;
+    use crate::data::authentication::{
+        AuthType, AuthenticationPage, FIRST_PAGE_DATA_BYTE_SIZE, PAGE_DATA_BYTE_SIZE,
+    };
+    use crate::data::uas_id::{SerialNumber, UasId};
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_720_101_954 + seconds, 0).unwrap()
+    }
+
+    fn basic_id(serial_number: &str) -> RemoteIDMessage {
+        RemoteIDMessage::BasicID(
+            BasicId::builder()
+                .serial_number(serial_number)
+                .build()
+                .unwrap(),
+        )
+    }
+
+    fn location(latitude: f64) -> RemoteIDMessage {
+        RemoteIDMessage::Location(
+            Location::builder()
+                .position(latitude, 8.9121736)
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn merge_messages_of_sender() {
+        let mut tracker = UasTracker::new(Duration::from_secs(10));
+
+        tracker.ingest(1, basic_id("1596F359746167260749"), at(0));
+        tracker.ingest(2, location(50.0), at(0));
+        tracker.ingest(
+            1,
+            RemoteIDMessage::MessagePack(alloc::vec![
+                location(49.8748544),
+                RemoteIDMessage::OperatorId(
+                    OperatorId::builder()
+                        .operator_id("FIN87astrdge12k8")
+                        .build()
+                        .unwrap()
+                ),
+            ]),
+            at(1),
+        );
+        let state = tracker
+            .ingest(1, basic_id("1596F359746167260748"), at(2))
+            .state;
+
+        assert_eq!(at(0), state.first_seen);
+        assert_eq!(at(2), state.last_seen);
+        assert_eq!(1, state.basic_ids.len());
+        assert_eq!(
+            UasId::SerialNumber(SerialNumber::parse("1596F359746167260748").unwrap()),
+            state.basic_id(IdType::SerialNumber).unwrap().uas_id
+        );
+        let location = state.location.as_ref().unwrap();
+        assert_eq!(Some(49.8748544), location.message.latidute);
+        assert_eq!(at(1), location.received);
+        assert!(state.operator_id.is_some());
+        assert!(state.system.is_none());
+        assert_eq!(2, tracker.len());
+    }
+
+    #[test]
+    fn keep_newer_message_on_replay() {
+        let mut tracker = UasTracker::new(Duration::from_secs(10));
+
+        tracker.ingest(1, location(50.0), at(5));
+        let state = tracker.ingest(1, location(51.0), at(3)).state;
+
+        assert_eq!(
+            Some(50.0),
+            state.location.as_ref().unwrap().message.latidute
+        );
+        assert_eq!(at(3), state.first_seen);
+        assert_eq!(at(5), state.last_seen);
+    }
+
+    #[test]
+    fn reassemble_authentication() {
+        let mut tracker = UasTracker::new(Duration::from_secs(10));
+        let page = RemoteIDMessage::Authentication(Authentication {
+            auth_type: AuthType::UasIdSignature,
+            page: AuthenticationPage::First {
+                last_page_index: 0,
+                length: 3,
+                timestamp: at(0),
+                data: [1; FIRST_PAGE_DATA_BYTE_SIZE],
+            },
+            version: ProtocolVersion::default(),
+        });
+
+        let state = tracker.ingest(1, page, at(0)).state;
+        assert_eq!(
+            alloc::vec![1, 1, 1],
+            state.authentication.as_ref().unwrap().message.data
+        );
+    }
+
+    #[test]
+    fn reassemble_authentication_across_addresses() {
+        let mut tracker = UasTracker::new(Duration::from_secs(10));
+        let first = RemoteIDMessage::Authentication(Authentication {
+            auth_type: AuthType::UasIdSignature,
+            page: AuthenticationPage::First {
+                last_page_index: 1,
+                length: 20,
+                timestamp: at(0),
+                data: [1; FIRST_PAGE_DATA_BYTE_SIZE],
+            },
+            version: ProtocolVersion::default(),
+        });
+        let second = RemoteIDMessage::Authentication(Authentication {
+            auth_type: AuthType::UasIdSignature,
+            page: AuthenticationPage::Subsequent {
+                page_number: 1,
+                data: [2; PAGE_DATA_BYTE_SIZE],
+            },
+            version: ProtocolVersion::default(),
+        });
+
+        tracker.ingest(1, basic_id("1596F359746167260749"), at(0));
+        tracker.ingest(1, first, at(0));
+        tracker.ingest(2, second, at(1));
+        let ingested = tracker.ingest(2, basic_id("1596F359746167260749"), at(1));
+
+        assert_eq!(1, ingested.merges.len());
+        let auth = &ingested.state.authentication.as_ref().unwrap().message;
+        assert_eq!(
+            [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2],
+            auth.data[..]
+        );
+    }
+
+    #[test]
+    fn expire_stale_aircraft() {
+        let mut tracker = UasTracker::new(Duration::from_secs(10));
+
+        tracker.ingest("a", location(50.0), at(0));
+        tracker.ingest("b", location(50.0), at(5));
+
+        assert!(tracker.expire(at(10)).is_empty());
+        let expired = tracker.expire(at(11));
+        assert_eq!(1, expired.len());
+        assert_eq!("a", expired[0].address);
+        assert!(tracker.get(&"a").is_none());
+        assert!(tracker.get(&"b").is_some());
+    }
+
+    fn operator_id(operator_id: &str) -> RemoteIDMessage {
+        RemoteIDMessage::OperatorId(
+            OperatorId::builder()
+                .operator_id(operator_id)
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn associate_randomized_address_by_uas_id() {
+        let mut tracker = UasTracker::new(Duration::from_secs(10));
+
+        tracker.ingest(1, basic_id("1596F359746167260749"), at(0));
+        tracker.ingest(1, location(50.0), at(0));
+        let track_id = tracker.get(&1).unwrap().track_id;
+
+        // new address starts a new track until the aircraft identifies itself
+        let ingested = tracker.ingest(2, location(50.1), at(2));
+        assert_ne!(track_id, ingested.state.track_id);
+        let merged = ingested.state.track_id;
+        assert_eq!(2, tracker.len());
+
+        let ingested = tracker.ingest(2, basic_id("1596F359746167260749"), at(3));
+        assert_eq!(
+            alloc::vec![MergeEvent { track_id, merged }],
+            ingested.merges
+        );
+        let state = ingested.state;
+        assert_eq!(track_id, state.track_id);
+        assert_eq!(2, state.address);
+        assert_eq!(alloc::vec![1, 2], state.addresses);
+        assert_eq!(at(0), state.first_seen);
+        assert_eq!(
+            Some(50.1),
+            state.location.as_ref().unwrap().message.latidute
+        );
+
+        assert_eq!(1, tracker.len());
+        assert_eq!(track_id, tracker.get(&1).unwrap().track_id);
+        assert!(tracker.get_track(merged).is_none());
+
+        // addresses of merged tracks expire with the aircraft
+        assert_eq!(1, tracker.expire(at(20)).len());
+        assert!(tracker.get(&2).is_none());
+    }
+
+    #[test]
+    fn associate_by_operator_id() {
+        let mut tracker = UasTracker::new(Duration::from_secs(10));
+
+        // the operator ID alone does not tell aircraft of the same operator apart
+        tracker.ingest(1, operator_id("FIN87astrdge12k8"), at(0));
+        let merges = tracker
+            .ingest(2, operator_id("FIN87astrdge12k8"), at(1))
+            .merges;
+        assert!(merges.is_empty());
+        assert_eq!(2, tracker.len());
+
+        // the track without UAS ID joins the identified aircraft
+        let merges = tracker
+            .ingest(1, basic_id("1596F359746167260749"), at(2))
+            .merges;
+        assert_eq!(1, merges.len());
+        assert_eq!(1, tracker.len());
+
+        // a second aircraft of the same operator is told apart by its UAS ID
+        tracker.ingest(3, basic_id("1596F359746167260748"), at(3));
+        let merges = tracker
+            .ingest(3, operator_id("FIN87astrdge12k8"), at(3))
+            .merges;
+        assert!(merges.is_empty());
+        assert_eq!(2, tracker.len());
+
+        // ambiguous which of both aircraft it is
+        let merges = tracker
+            .ingest(4, operator_id("FIN87astrdge12k8"), at(4))
+            .merges;
+        assert!(merges.is_empty());
+        assert_eq!(3, tracker.len());
+    }
+}
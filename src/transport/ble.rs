@@ -0,0 +1,253 @@
+//! Remote ID in Bluetooth LE advertising payloads
+//!
+//! A payload is a sequence of AD structures, each made of a length byte, the AD type and
+//! its data. Remote ID is carried as service data of the 16 bit UUID 0xFFFA:
+//!
+//! | Length | AD type | UUID  | App code | Message counter | Message      |
+//! |--------|---------|-------|----------|-----------------|--------------|
+//! | 1 byte | 0x16    | FA FF | 0x0D     | 1 byte          | 25 bytes, or a message pack with extended advertising |
+
+use alloc::vec::Vec;
+
+use crate::codec::decode::{from_service_data_frame, DecodeError};
+use crate::codec::encode::{try_to_extended_service_data, try_to_service_data, EncodeError};
+use crate::codec::ServiceDataFrame;
+use crate::data::RemoteIDMessage;
+
+pub const AD_TYPE_FLAGS: u8 = 0x01;
+pub const AD_TYPE_SHORTENED_LOCAL_NAME: u8 = 0x08;
+pub const AD_TYPE_COMPLETE_LOCAL_NAME: u8 = 0x09;
+pub const AD_TYPE_SERVICE_DATA_16: u8 = 0x16;
+pub const AD_TYPE_MANUFACTURER_DATA: u8 = 0xFF;
+
+/// 16 bit UUID of the Remote ID service data
+pub const REMOTE_ID_SERVICE_UUID_16: u16 = 0xFFFA;
+
+/// Size of a legacy advertising payload
+pub const LEGACY_PAYLOAD_BYTE_SIZE: usize = 31;
+
+/// Largest payload of extended advertising
+pub const MAX_EXTENDED_PAYLOAD_BYTE_SIZE: usize = 254;
+
+/// Length, AD type and UUID preceding the Remote ID service data
+const SERVICE_DATA_HEADER_BYTE_SIZE: usize = 4;
+
+/// A single AD structure of an advertising payload
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AdStructure<'a> {
+    Flags(u8),
+    /// Shortened or complete local name, not necessarily valid UTF-8
+    LocalName(&'a [u8]),
+    ServiceData16 {
+        uuid: u16,
+        data: &'a [u8],
+    },
+    ManufacturerData {
+        company_id: u16,
+        data: &'a [u8],
+    },
+    /// All other AD types, and AD structures too short for their type
+    Other {
+        ad_type: u8,
+        data: &'a [u8],
+    },
+}
+
+impl<'a> AdStructure<'a> {
+    fn parse(ad_type: u8, data: &'a [u8]) -> Self {
+        match (ad_type, data) {
+            (AD_TYPE_FLAGS, &[flags, ..]) => AdStructure::Flags(flags),
+            (AD_TYPE_SHORTENED_LOCAL_NAME | AD_TYPE_COMPLETE_LOCAL_NAME, name) => {
+                AdStructure::LocalName(name)
+            }
+            (AD_TYPE_SERVICE_DATA_16, &[lo, hi, ref data @ ..]) => AdStructure::ServiceData16 {
+                uuid: u16::from_le_bytes([lo, hi]),
+                data,
+            },
+            (AD_TYPE_MANUFACTURER_DATA, &[lo, hi, ref data @ ..]) => {
+                AdStructure::ManufacturerData {
+                    company_id: u16::from_le_bytes([lo, hi]),
+                    data,
+                }
+            }
+            (ad_type, data) => AdStructure::Other { ad_type, data },
+        }
+    }
+}
+
+/// Iterates over the AD structures of a legacy or extended advertising payload
+///
+/// Stops at the first malformed AD structure, or at zero padding.
+#[derive(Debug, Clone)]
+pub struct AdStructures<'a> {
+    payload: &'a [u8],
+}
+
+impl<'a> Iterator for AdStructures<'a> {
+    type Item = Result<AdStructure<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&len, rest) = self.payload.split_first()?;
+        // legacy payloads may be padded with zeros
+        if len == 0 {
+            self.payload = &[];
+            return None;
+        }
+
+        let Some((structure, rest)) = rest.split_at_checked(len as usize) else {
+            self.payload = &[];
+            return Some(Err(DecodeError::InvalidElement));
+        };
+        self.payload = rest;
+
+        let (&ad_type, data) = structure.split_first()?;
+        Some(Ok(AdStructure::parse(ad_type, data)))
+    }
+}
+
+pub fn ad_structures(payload: &[u8]) -> AdStructures<'_> {
+    AdStructures { payload }
+}
+
+/// Find the Remote ID service data, starting with the app code
+pub fn find_service_data(payload: &[u8]) -> Result<&[u8], DecodeError> {
+    for structure in ad_structures(payload) {
+        if let AdStructure::ServiceData16 {
+            uuid: REMOTE_ID_SERVICE_UUID_16,
+            data,
+        } = structure?
+        {
+            return Ok(data);
+        }
+    }
+    Err(DecodeError::MissingRemoteId)
+}
+
+/// Decode the Remote ID service data of a legacy or extended advertising payload
+pub fn from_advertising_payload(payload: &[u8]) -> Result<ServiceDataFrame, DecodeError> {
+    from_service_data_frame(find_service_data(payload)?)
+}
+
+/// Legacy advertising payload of a single message, which fills all 31 bytes
+pub fn to_legacy_payload(
+    msg: &RemoteIDMessage,
+    counter: u8,
+) -> Result<[u8; LEGACY_PAYLOAD_BYTE_SIZE], EncodeError> {
+    let service_data = try_to_service_data(msg, counter)?;
+
+    let mut payload = [0; LEGACY_PAYLOAD_BYTE_SIZE];
+    payload[..SERVICE_DATA_HEADER_BYTE_SIZE]
+        .copy_from_slice(&service_data_header(service_data.len()));
+    payload[SERVICE_DATA_HEADER_BYTE_SIZE..].copy_from_slice(&service_data);
+    Ok(payload)
+}
+
+/// Extended advertising payload, also for message packs
+pub fn to_extended_payload(msg: &RemoteIDMessage, counter: u8) -> Result<Vec<u8>, EncodeError> {
+    let service_data = try_to_extended_service_data(msg, counter)?;
+    if SERVICE_DATA_HEADER_BYTE_SIZE + service_data.len() > MAX_EXTENDED_PAYLOAD_BYTE_SIZE {
+        return Err(EncodeError::OutOfRange { field: "messages" });
+    }
+
+    let mut payload = service_data_header(service_data.len()).to_vec();
+    payload.extend_from_slice(&service_data);
+    Ok(payload)
+}
+
+fn service_data_header(service_data_len: usize) -> [u8; SERVICE_DATA_HEADER_BYTE_SIZE] {
+    let [uuid_lo, uuid_hi] = REMOTE_ID_SERVICE_UUID_16.to_le_bytes();
+    [
+        (service_data_len + 3) as u8,
+        AD_TYPE_SERVICE_DATA_16,
+        uuid_lo,
+        uuid_hi,
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::basic_id::BasicId;
+    use crate::data::location::Location;
+
+    fn basic_id() -> RemoteIDMessage {
+        RemoteIDMessage::BasicID(
+            BasicId::builder()
+                .serial_number("1596F359746167260749")
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn legacy_payload_roundtrip() {
+        let payload = to_legacy_payload(&basic_id(), 3).unwrap();
+        assert_eq!([0x1E, 0x16, 0xFA, 0xFF, 0x0D, 3, 0x02], payload[..7]);
+
+        let frame = from_advertising_payload(&payload).unwrap();
+        assert_eq!(3, frame.counter);
+        assert_eq!(basic_id(), frame.message);
+    }
+
+    #[test]
+    fn extended_payload_with_message_pack() {
+        let pack = RemoteIDMessage::MessagePack(alloc::vec![
+            basic_id(),
+            RemoteIDMessage::Location(Location::builder().build().unwrap()),
+        ]);
+        let payload = to_extended_payload(&pack, 9).unwrap();
+        assert_eq!([58, 0x16, 0xFA, 0xFF, 0x0D, 9, 0xF2, 25, 2], payload[..9]);
+        assert_eq!(59, payload.len());
+
+        assert_eq!(pack, from_advertising_payload(&payload).unwrap().message);
+        assert!(to_legacy_payload(&pack, 9).is_err());
+    }
+
+    #[test]
+    fn parse_ad_structures() {
+        let mut payload = alloc::vec![
+            0x02, 0x01, 0x06, // flags
+            0x04, 0x09, b'R', b'I', b'D', // complete local name
+            0x05, 0xFF, 0x4C, 0x00, 0x01, 0x02, // manufacturer data
+            0x03, 0x16, 0x0F, 0x18, // service data of another service
+        ];
+        let legacy = to_legacy_payload(&basic_id(), 0).unwrap();
+        payload.extend_from_slice(&legacy);
+
+        let structures = ad_structures(&payload)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(5, structures.len());
+        assert_eq!(AdStructure::Flags(0x06), structures[0]);
+        assert_eq!(AdStructure::LocalName(b"RID"), structures[1]);
+        assert_eq!(
+            AdStructure::ManufacturerData {
+                company_id: 0x004C,
+                data: &[0x01, 0x02]
+            },
+            structures[2]
+        );
+        assert_eq!(
+            AdStructure::ServiceData16 {
+                uuid: 0x180F,
+                data: &[]
+            },
+            structures[3]
+        );
+        assert_eq!(&legacy[4..], find_service_data(&payload).unwrap());
+    }
+
+    #[test]
+    fn reject_malformed_payload() {
+        // padding ends the payload
+        assert_eq!(
+            Err(DecodeError::MissingRemoteId),
+            from_advertising_payload(&[0x02, 0x01, 0x06, 0x00, 0x00])
+        );
+        // AD structure longer than the payload
+        assert_eq!(
+            Err(DecodeError::InvalidElement),
+            from_advertising_payload(&[0x02, 0x01, 0x06, 0x1E, 0x16, 0xFA, 0xFF])
+        );
+    }
+}
@@ -0,0 +1,353 @@
+//! HCI command packets to advertise Remote ID without a Bluetooth stack
+//!
+//! The packets start with the HCI command packet indicator, so they can be written to a raw
+//! HCI socket or UART as they are. The advertising data is built with
+//! [`to_legacy_payload`](super::ble::to_legacy_payload) or
+//! [`to_extended_payload`](super::ble::to_extended_payload).
+//!
+//! Legacy advertising:
+//! 1. [`le_set_advertising_parameters`]
+//! 2. [`le_set_advertising_data`]
+//! 3. [`le_set_advertising_enable`]
+//!
+//! Extended advertising, e.g. long range on the coded PHY:
+//! 1. [`le_set_extended_advertising_parameters`]
+//! 2. [`le_set_extended_advertising_data`]
+//! 3. [`le_set_extended_advertising_enable`]
+
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::codec::encode::EncodeError;
+
+use super::ble::LEGACY_PAYLOAD_BYTE_SIZE;
+
+/// Packet indicator of HCI command packets
+pub const HCI_COMMAND_PACKET: u8 = 0x01;
+
+/// Opcode group of the LE controller commands
+const OGF_LE: u16 = 0x08;
+
+pub const OPCODE_LE_SET_ADVERTISING_PARAMETERS: u16 = opcode(0x0006);
+pub const OPCODE_LE_SET_ADVERTISING_DATA: u16 = opcode(0x0008);
+pub const OPCODE_LE_SET_ADVERTISING_ENABLE: u16 = opcode(0x000A);
+pub const OPCODE_LE_SET_EXTENDED_ADVERTISING_PARAMETERS: u16 = opcode(0x0036);
+pub const OPCODE_LE_SET_EXTENDED_ADVERTISING_DATA: u16 = opcode(0x0037);
+pub const OPCODE_LE_SET_EXTENDED_ADVERTISING_ENABLE: u16 = opcode(0x0039);
+
+/// Most advertising data a single LE Set Extended Advertising Data command carries
+pub const MAX_EXTENDED_DATA_BYTE_SIZE: usize = 251;
+
+/// Non-connectable undirected advertising
+const ADV_NONCONN_IND: u8 = 0x03;
+
+/// Advertise on channels 37, 38 and 39
+const ALL_CHANNELS: u8 = 0x07;
+
+/// Complete extended advertising data in a single command
+const OPERATION_COMPLETE_DATA: u8 = 0x03;
+
+/// The controller should not fragment the data
+const FRAGMENT_PREFERENCE_NONE: u8 = 0x01;
+
+/// The host has no preference for the transmit power
+const TX_POWER_NO_PREFERENCE: u8 = 0x7F;
+
+const fn opcode(ocf: u16) -> u16 {
+    (OGF_LE << 10) | ocf
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OwnAddressType {
+    Public,
+    Random,
+}
+
+impl From<OwnAddressType> for u8 {
+    fn from(val: OwnAddressType) -> Self {
+        match val {
+            OwnAddressType::Public => 0x00,
+            OwnAddressType::Random => 0x01,
+        }
+    }
+}
+
+/// PHY of extended advertising, LE 2M only applies to the secondary channels
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Phy {
+    Le1M,
+    Le2M,
+    /// Long range, on both the primary and the secondary channels
+    LeCoded,
+}
+
+impl From<Phy> for u8 {
+    fn from(val: Phy) -> Self {
+        match val {
+            Phy::Le1M => 0x01,
+            Phy::Le2M => 0x02,
+            Phy::LeCoded => 0x03,
+        }
+    }
+}
+
+/// Non-connectable advertising on all channels, the interval in steps of 0.625 ms from
+/// 20 ms to 10.24 s
+pub fn le_set_advertising_parameters(
+    interval_min: Duration,
+    interval_max: Duration,
+    own_address_type: OwnAddressType,
+) -> Result<Vec<u8>, EncodeError> {
+    let interval_min = interval_units(interval_min, 0x0020..=0x4000)? as u16;
+    let interval_max = interval_units(interval_max, 0x0020..=0x4000)? as u16;
+
+    let mut params = Vec::with_capacity(15);
+    params.extend_from_slice(&interval_min.to_le_bytes());
+    params.extend_from_slice(&interval_max.to_le_bytes());
+    params.push(ADV_NONCONN_IND);
+    params.push(own_address_type.into());
+    // peer address type and address, unused for undirected advertising
+    params.extend_from_slice(&[0; 7]);
+    params.push(ALL_CHANNELS);
+    // no filter policy
+    params.push(0x00);
+
+    Ok(command(OPCODE_LE_SET_ADVERTISING_PARAMETERS, &params))
+}
+
+/// Set legacy advertising data of at most 31 bytes
+pub fn le_set_advertising_data(payload: &[u8]) -> Result<Vec<u8>, EncodeError> {
+    if payload.len() > LEGACY_PAYLOAD_BYTE_SIZE {
+        return Err(EncodeError::OutOfRange { field: "payload" });
+    }
+
+    // the data is always 31 bytes, padded with zeros
+    let mut params = [0; LEGACY_PAYLOAD_BYTE_SIZE + 1];
+    params[0] = payload.len() as u8;
+    params[1..=payload.len()].copy_from_slice(payload);
+
+    Ok(command(OPCODE_LE_SET_ADVERTISING_DATA, &params))
+}
+
+pub fn le_set_advertising_enable(enable: bool) -> Vec<u8> {
+    command(OPCODE_LE_SET_ADVERTISING_ENABLE, &[enable as u8])
+}
+
+/// Non-connectable, non-scannable extended advertising on all channels, the interval in
+/// steps of 0.625 ms from 20 ms
+pub fn le_set_extended_advertising_parameters(
+    handle: u8,
+    interval_min: Duration,
+    interval_max: Duration,
+    own_address_type: OwnAddressType,
+    phy: Phy,
+) -> Result<Vec<u8>, EncodeError> {
+    let interval_min = interval_units(interval_min, 0x0020..=0xFF_FFFF)?;
+    let interval_max = interval_units(interval_max, 0x0020..=0xFF_FFFF)?;
+    // only LE 1M and LE Coded are allowed on the primary channels
+    let primary_phy = match phy {
+        Phy::LeCoded => Phy::LeCoded,
+        Phy::Le1M | Phy::Le2M => Phy::Le1M,
+    };
+
+    let mut params = Vec::with_capacity(25);
+    params.push(handle);
+    // event properties: non-connectable, non-scannable, undirected
+    params.extend_from_slice(&[0x00, 0x00]);
+    params.extend_from_slice(&interval_min.to_le_bytes()[..3]);
+    params.extend_from_slice(&interval_max.to_le_bytes()[..3]);
+    params.push(ALL_CHANNELS);
+    params.push(own_address_type.into());
+    // peer address type and address, unused for undirected advertising
+    params.extend_from_slice(&[0; 7]);
+    // no filter policy
+    params.push(0x00);
+    params.push(TX_POWER_NO_PREFERENCE);
+    params.push(primary_phy.into());
+    // secondary advertising max skip
+    params.push(0x00);
+    params.push(phy.into());
+    // advertising SID
+    params.push(0x00);
+    // no scan request notifications
+    params.push(0x00);
+
+    Ok(command(
+        OPCODE_LE_SET_EXTENDED_ADVERTISING_PARAMETERS,
+        &params,
+    ))
+}
+
+/// Set the complete extended advertising data of at most 251 bytes
+pub fn le_set_extended_advertising_data(
+    handle: u8,
+    payload: &[u8],
+) -> Result<Vec<u8>, EncodeError> {
+    if payload.len() > MAX_EXTENDED_DATA_BYTE_SIZE {
+        return Err(EncodeError::OutOfRange { field: "payload" });
+    }
+
+    let mut params = Vec::with_capacity(4 + payload.len());
+    params.push(handle);
+    params.push(OPERATION_COMPLETE_DATA);
+    params.push(FRAGMENT_PREFERENCE_NONE);
+    params.push(payload.len() as u8);
+    params.extend_from_slice(payload);
+
+    Ok(command(OPCODE_LE_SET_EXTENDED_ADVERTISING_DATA, &params))
+}
+
+/// Enable or disable a single advertising set until disabled again
+pub fn le_set_extended_advertising_enable(handle: u8, enable: bool) -> Vec<u8> {
+    command(
+        OPCODE_LE_SET_EXTENDED_ADVERTISING_ENABLE,
+        &[
+            enable as u8,
+            // number of sets
+            0x01,
+            handle,
+            // no duration and no maximum number of events
+            0x00,
+            0x00,
+            0x00,
+        ],
+    )
+}
+
+/// Intervals are given in steps of 0.625 ms
+fn interval_units(
+    interval: Duration,
+    range: core::ops::RangeInclusive<u32>,
+) -> Result<u32, EncodeError> {
+    let units = interval.as_micros() / 625;
+    if !range.contains(&units.try_into().unwrap_or(u32::MAX)) {
+        return Err(EncodeError::OutOfRange { field: "interval" });
+    }
+    Ok(units as u32)
+}
+
+fn command(opcode: u16, params: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(4 + params.len());
+    packet.push(HCI_COMMAND_PACKET);
+    packet.extend_from_slice(&opcode.to_le_bytes());
+    packet.push(params.len() as u8);
+    packet.extend_from_slice(params);
+    packet
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::location::Location;
+    use crate::data::RemoteIDMessage;
+    use crate::transport::ble::{to_extended_payload, to_legacy_payload};
+
+    fn location() -> RemoteIDMessage {
+        RemoteIDMessage::Location(Location::builder().build().unwrap())
+    }
+
+    #[test]
+    fn legacy_advertising_commands() {
+        #[rustfmt::skip]
+        assert_eq!(
+            alloc::vec![
+                0x01, 0x06, 0x20, 15,
+                0xA0, 0x00, 0xF0, 0x00, 0x03, 0x01,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x07, 0x00,
+            ],
+            le_set_advertising_parameters(
+                Duration::from_millis(100),
+                Duration::from_millis(150),
+                OwnAddressType::Random
+            )
+            .unwrap()
+        );
+
+        let payload = to_legacy_payload(&location(), 5).unwrap();
+        let packet = le_set_advertising_data(&payload).unwrap();
+        assert_eq!([0x01, 0x08, 0x20, 32, 31], packet[..5]);
+        assert_eq!(payload, packet[5..]);
+
+        assert_eq!(
+            alloc::vec![0x01, 0x0A, 0x20, 1, 1],
+            le_set_advertising_enable(true)
+        );
+        assert_eq!(
+            alloc::vec![0x01, 0x0A, 0x20, 1, 0],
+            le_set_advertising_enable(false)
+        );
+    }
+
+    #[test]
+    fn short_legacy_data_is_padded() {
+        let packet = le_set_advertising_data(&[0x02, 0x01, 0x06]).unwrap();
+        assert_eq!(4 + 32, packet.len());
+        assert_eq!([3, 0x02, 0x01, 0x06, 0x00], packet[4..9]);
+
+        assert_eq!(
+            Err(EncodeError::OutOfRange { field: "payload" }),
+            le_set_advertising_data(&[0; 32])
+        );
+    }
+
+    #[test]
+    fn extended_advertising_commands() {
+        #[rustfmt::skip]
+        assert_eq!(
+            alloc::vec![
+                0x01, 0x36, 0x20, 25,
+                0x01, 0x00, 0x00, 0xA0, 0x00, 0x00, 0xA0, 0x00, 0x00, 0x07, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x7F, 0x03, 0x00, 0x03, 0x00, 0x00,
+            ],
+            le_set_extended_advertising_parameters(
+                1,
+                Duration::from_millis(100),
+                Duration::from_millis(100),
+                OwnAddressType::Public,
+                Phy::LeCoded
+            )
+            .unwrap()
+        );
+
+        let pack = RemoteIDMessage::MessagePack(alloc::vec![location(), location()]);
+        let payload = to_extended_payload(&pack, 0).unwrap();
+        let packet = le_set_extended_advertising_data(1, &payload).unwrap();
+        assert_eq!(
+            [
+                0x01,
+                0x37,
+                0x20,
+                4 + payload.len() as u8,
+                1,
+                0x03,
+                0x01,
+                payload.len() as u8
+            ],
+            packet[..8]
+        );
+        assert_eq!(payload, packet[8..]);
+
+        assert_eq!(
+            alloc::vec![0x01, 0x39, 0x20, 6, 1, 1, 1, 0, 0, 0],
+            le_set_extended_advertising_enable(1, true)
+        );
+    }
+
+    #[test]
+    fn reject_out_of_range_interval() {
+        assert!(le_set_advertising_parameters(
+            Duration::from_millis(10),
+            Duration::from_millis(100),
+            OwnAddressType::Public
+        )
+        .is_err());
+        assert!(le_set_advertising_parameters(
+            Duration::from_secs(11),
+            Duration::from_secs(11),
+            OwnAddressType::Public
+        )
+        .is_err());
+    }
+}
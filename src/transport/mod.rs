@@ -0,0 +1,6 @@
+//! Framing of Remote ID messages for the broadcast transports
+
+pub mod ble;
+pub mod hci;
+pub mod wifi_beacon;
+pub mod wifi_nan;
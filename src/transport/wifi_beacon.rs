@@ -0,0 +1,174 @@
+//! Remote ID in Wi-Fi beacon frames
+//!
+//! The messages are carried by a vendor specific information element:
+//!
+//! | Element ID | Length | OUI      | OUI type | Message counter | Message pack |
+//! |------------|--------|----------|----------|-----------------|--------------|
+//! | 0xDD       | 1 byte | FA-0B-BC | 0x0D     | 1 byte          | 3 + n * 25   |
+
+use alloc::vec::Vec;
+
+use crate::codec::decode::{from_message_buffer, DecodeError};
+use crate::codec::encode::{message_buffer_size, try_to_message_buffer, EncodeError};
+use crate::codec::ServiceDataFrame;
+use crate::data::RemoteIDMessage;
+
+pub const ELEMENT_ID_VENDOR_SPECIFIC: u8 = 0xDD;
+
+/// OUI of ASD-STAN
+pub const OUI: [u8; 3] = [0xFA, 0x0B, 0xBC];
+
+pub const OUI_TYPE: u8 = 0x0D;
+
+/// Element ID and length
+pub const ELEMENT_HEADER_BYTE_SIZE: usize = 2;
+
+/// OUI, OUI type and message counter
+const VENDOR_HEADER_BYTE_SIZE: usize = 5;
+
+/// Timestamp, beacon interval and capability information preceding the elements
+pub const BEACON_FIXED_FIELDS_BYTE_SIZE: usize = 12;
+
+/// Vendor specific element carrying the messages, usually a message pack
+pub fn to_vendor_element(msg: &RemoteIDMessage, counter: u8) -> Result<Vec<u8>, EncodeError> {
+    let len = VENDOR_HEADER_BYTE_SIZE + message_buffer_size(msg);
+    if len > u8::MAX as usize {
+        return Err(EncodeError::OutOfRange { field: "messages" });
+    }
+
+    let mut element = alloc::vec![0; ELEMENT_HEADER_BYTE_SIZE + len];
+    element[0] = ELEMENT_ID_VENDOR_SPECIFIC;
+    element[1] = len as u8;
+    element[2..5].copy_from_slice(&OUI);
+    element[5] = OUI_TYPE;
+    element[6] = counter;
+    try_to_message_buffer(msg, &mut element[7..])?;
+
+    Ok(element)
+}
+
+/// Decode a vendor specific element, starting with the element ID
+pub fn from_vendor_element(element: &[u8]) -> Result<ServiceDataFrame, DecodeError> {
+    let (id, body) = split_element(element)?;
+    if id != ELEMENT_ID_VENDOR_SPECIFIC || !is_remote_id(body) {
+        return Err(DecodeError::MissingRemoteId);
+    }
+    from_vendor_body(body)
+}
+
+/// Find the Remote ID element in the body of a beacon frame, the frame without MAC header
+pub fn find_vendor_element(beacon_body: &[u8]) -> Result<&[u8], DecodeError> {
+    let mut elements =
+        beacon_body
+            .get(BEACON_FIXED_FIELDS_BYTE_SIZE..)
+            .ok_or(DecodeError::BufferTooShort {
+                expected: BEACON_FIXED_FIELDS_BYTE_SIZE,
+                actual: beacon_body.len(),
+            })?;
+
+    while !elements.is_empty() {
+        let (id, body) = split_element(elements)?;
+        let (element, rest) = elements.split_at(ELEMENT_HEADER_BYTE_SIZE + body.len());
+        if id == ELEMENT_ID_VENDOR_SPECIFIC && is_remote_id(body) {
+            return Ok(element);
+        }
+        elements = rest;
+    }
+    Err(DecodeError::MissingRemoteId)
+}
+
+/// Decode the Remote ID element of a beacon frame body
+pub fn from_beacon_body(beacon_body: &[u8]) -> Result<ServiceDataFrame, DecodeError> {
+    from_vendor_element(find_vendor_element(beacon_body)?)
+}
+
+/// Element ID and body of the first element
+fn split_element(elements: &[u8]) -> Result<(u8, &[u8]), DecodeError> {
+    let [id, len, ..] = *elements else {
+        return Err(DecodeError::InvalidElement);
+    };
+    let body = elements
+        .get(ELEMENT_HEADER_BYTE_SIZE..ELEMENT_HEADER_BYTE_SIZE + len as usize)
+        .ok_or(DecodeError::InvalidElement)?;
+    Ok((id, body))
+}
+
+fn is_remote_id(vendor_body: &[u8]) -> bool {
+    vendor_body.starts_with(&OUI) && vendor_body.get(3) == Some(&OUI_TYPE)
+}
+
+fn from_vendor_body(body: &[u8]) -> Result<ServiceDataFrame, DecodeError> {
+    if body.len() < VENDOR_HEADER_BYTE_SIZE {
+        return Err(DecodeError::InvalidElement);
+    }
+    Ok(ServiceDataFrame {
+        counter: body[4],
+        message: from_message_buffer(&body[VENDOR_HEADER_BYTE_SIZE..])?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::basic_id::BasicId;
+    use crate::data::location::Location;
+
+    fn message_pack() -> RemoteIDMessage {
+        RemoteIDMessage::MessagePack(alloc::vec![
+            RemoteIDMessage::BasicID(
+                BasicId::builder()
+                    .serial_number("1596F359746167260749")
+                    .build()
+                    .unwrap(),
+            ),
+            RemoteIDMessage::Location(
+                Location::builder()
+                    .position(49.8748544, 8.9121736)
+                    .build()
+                    .unwrap(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn vendor_element_roundtrip() {
+        let element = to_vendor_element(&message_pack(), 7).unwrap();
+
+        assert_eq!(
+            [0xDD, 58, 0xFA, 0x0B, 0xBC, 0x0D, 7, 0xF2, 25, 2],
+            element[..10]
+        );
+        assert_eq!(60, element.len());
+
+        let frame = from_vendor_element(&element).unwrap();
+        assert_eq!(7, frame.counter);
+        assert_eq!(message_pack(), frame.message);
+    }
+
+    #[test]
+    fn extract_from_beacon_body() {
+        let mut body = alloc::vec![0; BEACON_FIXED_FIELDS_BYTE_SIZE];
+        // SSID
+        body.extend_from_slice(&[0x00, 4, b'd', b'r', b'o', b'n']);
+        // vendor specific element of another vendor
+        body.extend_from_slice(&[0xDD, 5, 0x00, 0x50, 0xF2, 0x04, 0x00]);
+        body.extend_from_slice(&to_vendor_element(&message_pack(), 1).unwrap());
+        // supported rates
+        body.extend_from_slice(&[0x01, 1, 0x82]);
+
+        let frame = from_beacon_body(&body).unwrap();
+        assert_eq!(1, frame.counter);
+        assert_eq!(message_pack(), frame.message);
+    }
+
+    #[test]
+    fn reject_malformed_beacon_body() {
+        let mut body = alloc::vec![0; BEACON_FIXED_FIELDS_BYTE_SIZE];
+        body.extend_from_slice(&[0x00, 4, b'd', b'r', b'o', b'n']);
+        assert_eq!(Err(DecodeError::MissingRemoteId), from_beacon_body(&body));
+
+        // element longer than the frame
+        body.extend_from_slice(&[0xDD, 60, 0xFA, 0x0B, 0xBC, 0x0D]);
+        assert_eq!(Err(DecodeError::InvalidElement), from_beacon_body(&body));
+    }
+}
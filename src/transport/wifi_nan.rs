@@ -0,0 +1,230 @@
+//! Remote ID in Wi-Fi Neighbor Awareness Networking (NAN) service discovery frames
+//!
+//! The messages are published as service info of a service descriptor attribute in the
+//! body of a public action frame:
+//!
+//! | Category | Action | OUI      | OUI type | Attributes |
+//! |----------|--------|----------|----------|------------|
+//! | 0x04     | 0x09   | 50-6F-9A | 0x13     | ...        |
+//!
+//! | Attribute ID | Length  | Service ID | Instance ID | Requestor | Control | Info length | Counter | Message pack |
+//! |--------------|---------|------------|-------------|-----------|---------|-------------|---------|--------------|
+//! | 0x03         | 2 bytes | 6 bytes    | 1 byte      | 1 byte    | 1 byte  | 1 byte      | 1 byte  | 3 + n * 25   |
+
+use alloc::vec::Vec;
+
+use crate::codec::decode::{from_message_buffer, DecodeError};
+use crate::codec::encode::{message_buffer_size, try_to_message_buffer, EncodeError};
+use crate::codec::ServiceDataFrame;
+use crate::data::RemoteIDMessage;
+
+pub const CATEGORY_PUBLIC_ACTION: u8 = 0x04;
+
+pub const ACTION_VENDOR_SPECIFIC: u8 = 0x09;
+
+/// OUI of the Wi-Fi Alliance
+pub const OUI: [u8; 3] = [0x50, 0x6F, 0x9A];
+
+pub const OUI_TYPE_NAN: u8 = 0x13;
+
+pub const ATTRIBUTE_ID_SERVICE_DESCRIPTOR: u8 = 0x03;
+
+/// First 6 bytes of the SHA-256 hash of the service name "org.opendroneid.remoteid"
+pub const SERVICE_ID: [u8; 6] = [0x88, 0x69, 0x19, 0x9D, 0x92, 0x09];
+
+/// Service control type of a publish message
+const SERVICE_CONTROL_PUBLISH: u8 = 0x00;
+
+/// Service control bit signaling that service info is present
+const SERVICE_CONTROL_INFO_PRESENT: u8 = 0x10;
+
+/// Category, action, OUI and OUI type
+pub const FRAME_HEADER_BYTE_SIZE: usize = 6;
+
+/// Attribute ID and length
+pub const ATTRIBUTE_HEADER_BYTE_SIZE: usize = 3;
+
+/// Service ID, instance ID, requestor instance ID, service control and service info length
+const SERVICE_DESCRIPTOR_HEADER_BYTE_SIZE: usize = 10;
+
+/// Service discovery frame body carrying the messages, usually a message pack
+pub fn to_service_discovery_frame(
+    msg: &RemoteIDMessage,
+    counter: u8,
+) -> Result<Vec<u8>, EncodeError> {
+    let service_info_len = 1 + message_buffer_size(msg);
+    if service_info_len > u8::MAX as usize {
+        return Err(EncodeError::OutOfRange { field: "messages" });
+    }
+    let attribute_len = SERVICE_DESCRIPTOR_HEADER_BYTE_SIZE + service_info_len;
+
+    let mut frame =
+        Vec::with_capacity(FRAME_HEADER_BYTE_SIZE + ATTRIBUTE_HEADER_BYTE_SIZE + attribute_len);
+    frame.extend_from_slice(&[CATEGORY_PUBLIC_ACTION, ACTION_VENDOR_SPECIFIC]);
+    frame.extend_from_slice(&OUI);
+    frame.push(OUI_TYPE_NAN);
+
+    frame.push(ATTRIBUTE_ID_SERVICE_DESCRIPTOR);
+    frame.extend_from_slice(&(attribute_len as u16).to_le_bytes());
+    frame.extend_from_slice(&SERVICE_ID);
+    // instance ID, requestor instance ID
+    frame.extend_from_slice(&[0x01, 0x00]);
+    frame.push(SERVICE_CONTROL_PUBLISH | SERVICE_CONTROL_INFO_PRESENT);
+    frame.push(service_info_len as u8);
+    frame.push(counter);
+
+    let messages = frame.len();
+    frame.resize(messages + message_buffer_size(msg), 0);
+    try_to_message_buffer(msg, &mut frame[messages..])?;
+
+    Ok(frame)
+}
+
+/// Decode the Remote ID service descriptor of a service discovery frame body
+///
+/// The body starts with the category of the action frame, right after the MAC header.
+pub fn from_service_discovery_frame(frame: &[u8]) -> Result<ServiceDataFrame, DecodeError> {
+    let (header, mut attributes) =
+        frame
+            .split_at_checked(FRAME_HEADER_BYTE_SIZE)
+            .ok_or(DecodeError::BufferTooShort {
+                expected: FRAME_HEADER_BYTE_SIZE,
+                actual: frame.len(),
+            })?;
+    if header[..2] != [CATEGORY_PUBLIC_ACTION, ACTION_VENDOR_SPECIFIC]
+        || header[2..5] != OUI
+        || header[5] != OUI_TYPE_NAN
+    {
+        return Err(DecodeError::MissingRemoteId);
+    }
+
+    while !attributes.is_empty() {
+        let (id, body, rest) = split_attribute(attributes)?;
+        if id == ATTRIBUTE_ID_SERVICE_DESCRIPTOR && body.starts_with(&SERVICE_ID) {
+            return from_service_descriptor(body);
+        }
+        attributes = rest;
+    }
+    Err(DecodeError::MissingRemoteId)
+}
+
+/// Attribute ID, body and the following attributes
+fn split_attribute(attributes: &[u8]) -> Result<(u8, &[u8], &[u8]), DecodeError> {
+    let [id, len_lo, len_hi, ..] = *attributes else {
+        return Err(DecodeError::InvalidElement);
+    };
+    let end = ATTRIBUTE_HEADER_BYTE_SIZE + u16::from_le_bytes([len_lo, len_hi]) as usize;
+    let body = attributes
+        .get(ATTRIBUTE_HEADER_BYTE_SIZE..end)
+        .ok_or(DecodeError::InvalidElement)?;
+    Ok((id, body, &attributes[end..]))
+}
+
+fn from_service_descriptor(body: &[u8]) -> Result<ServiceDataFrame, DecodeError> {
+    if body.len() < SERVICE_DESCRIPTOR_HEADER_BYTE_SIZE {
+        return Err(DecodeError::InvalidElement);
+    }
+    let service_control = body[8];
+    if service_control & SERVICE_CONTROL_INFO_PRESENT == 0 {
+        return Err(DecodeError::MissingRemoteId);
+    }
+
+    let service_info_len = body[9] as usize;
+    let service_info = body
+        .get(
+            SERVICE_DESCRIPTOR_HEADER_BYTE_SIZE
+                ..SERVICE_DESCRIPTOR_HEADER_BYTE_SIZE + service_info_len,
+        )
+        .ok_or(DecodeError::InvalidElement)?;
+    let [counter, ref messages @ ..] = *service_info else {
+        return Err(DecodeError::InvalidElement);
+    };
+
+    Ok(ServiceDataFrame {
+        counter,
+        message: from_message_buffer(messages)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data::basic_id::BasicId;
+    use crate::data::operator_id::OperatorId;
+
+    fn message_pack() -> RemoteIDMessage {
+        RemoteIDMessage::MessagePack(alloc::vec![
+            RemoteIDMessage::BasicID(
+                BasicId::builder()
+                    .serial_number("1596F359746167260749")
+                    .build()
+                    .unwrap(),
+            ),
+            RemoteIDMessage::OperatorId(
+                OperatorId::builder()
+                    .operator_id("FIN87astrdge12k8")
+                    .build()
+                    .unwrap(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn service_discovery_frame_roundtrip() {
+        let frame = to_service_discovery_frame(&message_pack(), 42).unwrap();
+
+        #[rustfmt::skip]
+        let header = [
+            0x04, 0x09, 0x50, 0x6F, 0x9A, 0x13,
+            // service descriptor attribute of 64 bytes
+            0x03, 64, 0,
+            0x88, 0x69, 0x19, 0x9D, 0x92, 0x09, 0x01, 0x00, 0x10,
+            // service info of 54 bytes, counter and message pack of 2 messages
+            54, 42, 0xF2, 25, 2,
+        ];
+        assert_eq!(header, frame[..header.len()]);
+        assert_eq!(6 + 3 + 64, frame.len());
+
+        let received = from_service_discovery_frame(&frame).unwrap();
+        assert_eq!(42, received.counter);
+        assert_eq!(message_pack(), received.message);
+    }
+
+    #[test]
+    fn skip_other_attributes() {
+        let frame = to_service_discovery_frame(&message_pack(), 1).unwrap();
+        let (header, attribute) = frame.split_at(FRAME_HEADER_BYTE_SIZE);
+
+        let mut with_extension = header.to_vec();
+        // service descriptor extension attribute
+        with_extension.extend_from_slice(&[0x0E, 4, 0, 0x01, 0x00, 0x02, 0x00]);
+        with_extension.extend_from_slice(attribute);
+
+        let received = from_service_discovery_frame(&with_extension).unwrap();
+        assert_eq!(message_pack(), received.message);
+    }
+
+    #[test]
+    fn reject_other_frames() {
+        let mut frame = to_service_discovery_frame(&message_pack(), 1).unwrap();
+
+        frame[9] = 0;
+        assert_eq!(
+            Err(DecodeError::MissingRemoteId),
+            from_service_discovery_frame(&frame)
+        );
+
+        frame.truncate(20);
+        frame[9] = 0x88;
+        assert_eq!(
+            Err(DecodeError::InvalidElement),
+            from_service_discovery_frame(&frame)
+        );
+
+        frame[3] = 0;
+        assert_eq!(
+            Err(DecodeError::MissingRemoteId),
+            from_service_discovery_frame(&frame)
+        );
+    }
+}